@@ -44,6 +44,8 @@ impl<'p> Dump<'p> {
         match self.get_src() {
             SrcDir::Syntax(p) => {
                 let mut builder = SyntaxSetBuilder::default();
+                // fallback of unknown syntaxes
+                builder.add_plain_text_syntax();
                 builder
                     .add_from_folder(p, !self.get_exclude_newline())
                     .context("Failed to add syntax set from dir")?;
@@ -18,7 +18,8 @@ pub(crate) fn parse_args() -> anyhow::Result<()> {
     };
 
     if *args.get_version() {
-        return Ok(println!("{}", PKG_VERSION));
+        println!("{}", PKG_VERSION);
+        return Ok(());
     }
 
     let compression = *args.get_compress();
@@ -0,0 +1,300 @@
+use std::env;
+pub use syntect::highlighting::Color;
+
+/// How many colors are used for terminal output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorDepth {
+    /// 24-bit colors, e.g. `\x1B[38;2;255;0;0m`
+    #[default]
+    TrueColor,
+    /// The xterm 256-color palette, e.g. `\x1B[38;5;196m`
+    Ansi256,
+    /// The 16 basic colors, e.g. `\x1B[91m`
+    Ansi16,
+    /// No escape sequences at all.
+    NoColor,
+    /// Detects the color depth from the environment when writing to standard output. See [`ColorDepth::detect`].
+    ///
+    /// If a writer is provided, it is not considered a terminal, so only `CLICOLOR_FORCE` can enable colors.
+    Auto,
+}
+
+/// The default xterm values of the 16 basic colors.
+const ANSI16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The channel values of the 6x6x6 color cube (16..=231).
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// A cheap perceptual distance: green matters most, blue least.
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    2 * d(r1, r2) + 4 * d(g1, g2) + 3 * d(b1, b2)
+}
+
+fn nearest_cube_index(v: u8) -> usize {
+    match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        _ => (v as usize - 35) / 40,
+    }
+}
+
+/// Quantizes `c` to the nearest color of the xterm 256-color palette.
+///
+/// Only the color cube (16..=231) and the grayscale ramp (232..=255) are considered, because the first 16 colors are usually redefined by the terminal theme.
+///
+/// # Example
+///
+/// ```
+/// use hlight::color::{to_ansi256, Color};
+///
+/// let red = Color { r: 255, g: 0, b: 0, a: 255 };
+/// assert_eq!(to_ansi256(red), 196);
+/// ```
+pub fn to_ansi256(c: Color) -> u8 {
+    let rgb = (c.r, c.g, c.b);
+
+    let (ri, gi, bi) = (
+        nearest_cube_index(c.r),
+        nearest_cube_index(c.g),
+        nearest_cube_index(c.b),
+    );
+    let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let avg = (c.r as u32 + c.g as u32 + c.b as u32) / 3;
+    let gray_step = (avg.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + 10 * gray_step;
+    let gray = (gray_level, gray_level, gray_level);
+
+    if distance(rgb, gray) < distance(rgb, cube) {
+        232 + gray_step
+    } else {
+        cube_index as u8
+    }
+}
+
+/// Quantizes `c` to the nearest of the 16 basic colors (0..=15).
+///
+/// # Example
+///
+/// ```
+/// use hlight::color::{to_ansi16, Color};
+///
+/// let orange = Color { r: 242, g: 151, b: 24, a: 255 };
+/// assert_eq!(to_ansi16(orange), 3);
+/// ```
+pub fn to_ansi16(c: Color) -> u8 {
+    let rgb = (c.r, c.g, c.b);
+    (0..)
+        .zip(ANSI16)
+        .min_by_key(|&(_, x)| distance(rgb, x))
+        .map(|(i, _)| i)
+        .unwrap_or_default()
+}
+
+/// Blends a translucent foreground color with the background.
+pub(crate) fn blend_fg_color(fg: Color, bg: Color) -> Color {
+    if fg.a == 0xff {
+        return fg;
+    }
+    let ratio = fg.a as u32;
+    let blend = |f: u8, b: u8| {
+        ((f as u32 * ratio + b as u32 * (255 - ratio)) / 255) as u8
+    };
+    Color {
+        r: blend(fg.r, bg.r),
+        g: blend(fg.g, bg.g),
+        b: blend(fg.b, bg.b),
+        a: 255,
+    }
+}
+
+/// `#rrggbb`, or `#rrggbbaa` if the color is not opaque.
+pub(crate) fn hex_color(c: Color) -> String {
+    match c.a {
+        0xFF => format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b),
+        a => format!("#{:02x}{:02x}{:02x}{a:02x}", c.r, c.g, c.b),
+    }
+}
+
+impl ColorDepth {
+    /// Resolves [`ColorDepth::Auto`] to a concrete color depth. Other values are returned as is.
+    pub fn resolve(self, is_terminal: bool) -> Self {
+        match self {
+            Self::Auto => Self::detect(is_terminal),
+            x => x,
+        }
+    }
+
+    /// Detects the color depth from the environment variables and whether the destination is a terminal.
+    ///
+    /// 1. `NO_COLOR` (non-empty) disables colors.
+    /// 2. `CLICOLOR_FORCE` (non-empty and not "0") enables colors even if the destination is not a terminal.
+    /// 3. `CLICOLOR=0`, `TERM=dumb` or a non-terminal destination disables colors.
+    /// 4. `COLORTERM=truecolor` or `COLORTERM=24bit` enables 24-bit colors, a `TERM` containing "256" enables 256 colors, and everything else gets 16 colors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::color::ColorDepth;
+    /// use std::io::{stdout, IsTerminal};
+    ///
+    /// let depth = ColorDepth::detect(stdout().is_terminal());
+    /// assert_ne!(depth, ColorDepth::Auto);
+    /// ```
+    pub fn detect(is_terminal: bool) -> Self {
+        Self::detect_with(is_terminal, |k| env::var(k).ok())
+    }
+
+    fn detect_with<F>(is_terminal: bool, var: F) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let non_empty = |k| var(k).filter(|v| !v.is_empty());
+
+        if non_empty("NO_COLOR").is_some() {
+            return Self::NoColor;
+        }
+
+        let force = non_empty("CLICOLOR_FORCE").is_some_and(|v| v != "0");
+        let term = var("TERM").unwrap_or_default();
+
+        if !force
+            && (!is_terminal
+                || term == "dumb"
+                || var("CLICOLOR").is_some_and(|v| v == "0"))
+        {
+            return Self::NoColor;
+        }
+
+        match var("COLORTERM").as_deref() {
+            Some("truecolor" | "24bit") => Self::TrueColor,
+            _ if term.contains("256") => Self::Ansi256,
+            _ => Self::Ansi16,
+        }
+    }
+
+    /// Appends the SGR escape sequence that sets the foreground (or the background if `bg` is true) to `c`.
+    pub(crate) fn push_escape(self, s: &mut String, c: Color, bg: bool) {
+        use ColorDepth::*;
+
+        let code = match self {
+            TrueColor => {
+                let base = if bg { 48 } else { 38 };
+                format!("{base};2;{};{};{}", c.r, c.g, c.b)
+            }
+            Ansi256 => {
+                let base = if bg { 48 } else { 38 };
+                format!("{base};5;{}", to_ansi256(c))
+            }
+            Ansi16 => {
+                let (base, bright) = if bg { (40, 100) } else { (30, 90) };
+                match to_ansi16(c) {
+                    i @ 0..=7 => format!("{}", base + i),
+                    i => format!("{}", bright + i - 8),
+                }
+            }
+            NoColor | Auto => return,
+        };
+        s.push_str("\x1B[");
+        s.push_str(&code);
+        s.push('m');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 255 }
+    }
+
+    #[test]
+    fn ansi256() {
+        assert_eq!(to_ansi256(rgb(0, 0, 0)), 16);
+        assert_eq!(to_ansi256(rgb(255, 255, 255)), 231);
+        assert_eq!(to_ansi256(rgb(0, 0, 255)), 21);
+        assert_eq!(to_ansi256(rgb(128, 128, 128)), 244);
+        // Monokai Extended: keyword
+        assert_eq!(to_ansi256(rgb(0xF9, 0x26, 0x72)), 197);
+    }
+
+    #[test]
+    fn ansi16() {
+        assert_eq!(to_ansi16(rgb(0, 0, 0)), 0);
+        assert_eq!(to_ansi16(rgb(250, 10, 10)), 9);
+        assert_eq!(to_ansi16(rgb(0, 190, 0)), 2);
+        assert_eq!(to_ansi16(rgb(250, 250, 250)), 15);
+    }
+
+    #[test]
+    fn detect() {
+        use std::collections::HashMap;
+
+        let detect = |is_terminal, vars: &[(&str, &str)]| {
+            let vars: HashMap<_, _> = vars.iter().copied().collect();
+            ColorDepth::detect_with(is_terminal, |k| {
+                vars.get(k).map(|v| v.to_string())
+            })
+        };
+        let truecolor = ("COLORTERM", "truecolor");
+        let xterm256 = ("TERM", "xterm-256color");
+
+        assert_eq!(detect(true, &[truecolor]), ColorDepth::TrueColor);
+        assert_eq!(detect(true, &[xterm256]), ColorDepth::Ansi256);
+        assert_eq!(detect(true, &[("TERM", "linux")]), ColorDepth::Ansi16);
+        assert_eq!(detect(true, &[("TERM", "dumb")]), ColorDepth::NoColor);
+        assert_eq!(detect(false, &[truecolor]), ColorDepth::NoColor);
+        assert_eq!(
+            detect(true, &[truecolor, ("NO_COLOR", "1")]),
+            ColorDepth::NoColor
+        );
+        assert_eq!(
+            detect(true, &[truecolor, ("NO_COLOR", "")]),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            detect(false, &[xterm256, ("CLICOLOR_FORCE", "1")]),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(
+            detect(true, &[xterm256, ("CLICOLOR", "0")]),
+            ColorDepth::NoColor
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        let c = rgb(255, 0, 0);
+        let escape = |depth: ColorDepth, bg| {
+            let mut s = String::new();
+            depth.push_escape(&mut s, c, bg);
+            s
+        };
+
+        assert_eq!(escape(ColorDepth::TrueColor, false), "\x1B[38;2;255;0;0m");
+        assert_eq!(escape(ColorDepth::Ansi256, true), "\x1B[48;5;196m");
+        assert_eq!(escape(ColorDepth::Ansi16, false), "\x1B[91m");
+        assert_eq!(escape(ColorDepth::Ansi16, true), "\x1B[101m");
+        assert_eq!(escape(ColorDepth::NoColor, false), "");
+    }
+}
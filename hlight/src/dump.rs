@@ -0,0 +1,11 @@
+use bincode::{DefaultOptions, Options};
+
+/// The bincode options of `syntect::dumps::from_uncompressed_data`, but every read is limited by the length of `data`.
+///
+/// Without the limit, a corrupted length prefix (or a dump of another set type) makes bincode allocate a huge buffer and aborts the process instead of returning an error.
+pub(crate) fn dump_options(data: &[u8]) -> impl Options {
+    DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(data.len() as u64)
+}
@@ -0,0 +1,211 @@
+use std::{error, fmt, io};
+
+/// `Result<T, hlight::Error>`
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The error type of hlight.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Failed to write the output.
+    Io(io::Error),
+    /// syntect failed to parse or highlight the contents.
+    Parse(syntect::Error),
+    /// The theme name could not be found in the theme set.
+    UnknownTheme {
+        name: String,
+        /// All theme names of the theme set, sorted.
+        available: Vec<String>,
+        /// The closest available name, if there is a reasonably similar one.
+        suggestion: Option<String>,
+    },
+    /// The syntax could not be found in the syntax set.
+    UnknownSyntax {
+        name: String,
+        /// The closest syntax name or file extension, if there is a reasonably similar one.
+        suggestion: Option<String>,
+    },
+    /// Failed to load a syntax set or theme set from a packdump.
+    Load(LoadError),
+    /// The input is not valid UTF-8, and [`InvalidUtf8::Error`](crate::InvalidUtf8::Error) is used.
+    Utf8(std::str::Utf8Error),
+}
+
+/// Why a packdump could not be loaded.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LoadError {
+    /// The data is zlib-compressed, but an uncompressed dump is expected (i.e. `hlight-dump` without `--compress`).
+    Compressed,
+    /// The data is a dump of another set type, e.g. a theme set was loaded as a syntax set.
+    WrongSetType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The data is not valid bincode for the expected set type, e.g. it is corrupted or was dumped by an incompatible version of syntect.
+    InvalidData(Box<dyn error::Error + Send + Sync>),
+}
+
+impl LoadError {
+    /// Classifies a failed deserialization of `data`.
+    ///
+    /// `found` is the name of the other set type, if `data` can be deserialized as it.
+    pub(crate) fn new<E>(
+        data: &[u8],
+        err: E,
+        expected: &'static str,
+        found: Option<&'static str>,
+    ) -> Self
+    where
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+    {
+        match found {
+            _ if is_zlib(data) => Self::Compressed,
+            Some(found) => Self::WrongSetType { expected, found },
+            _ => Self::InvalidData(err.into()),
+        }
+    }
+}
+
+/// A zlib stream starts with CMF (0x78 for deflate with a 32K window) and FLG, and `CMF * 256 + FLG` is a multiple of 31.
+// `u16::is_multiple_of` needs Rust 1.87.
+#[allow(clippy::manual_is_multiple_of)]
+fn is_zlib(data: &[u8]) -> bool {
+    match data {
+        [cmf @ 0x78, flg, ..] => {
+            (*cmf as u16 * 256 + *flg as u16) % 31 == 0
+        }
+        _ => false,
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compressed => f.write_str(
+                "The dump data is compressed, but an uncompressed dump is expected",
+            ),
+            Self::WrongSetType { expected, found } => {
+                write!(f, "Expected a {expected} dump, but found a {found} dump")
+            }
+            Self::InvalidData(e) => write!(f, "Invalid dump data: {e}"),
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::InvalidData(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<LoadError> for Error {
+    fn from(e: LoadError) -> Self {
+        Self::Load(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            Self::Parse(e) => write!(f, "Failed to highlight: {e}"),
+            Self::UnknownTheme {
+                name,
+                available,
+                suggestion,
+            } => {
+                write!(f, "Unknown theme: {name:?}")?;
+                if let Some(s) = suggestion {
+                    write!(f, ", did you mean {s:?}?")?;
+                }
+                write!(f, " (available: {})", available.join(", "))
+            }
+            Self::UnknownSyntax { name, suggestion } => {
+                write!(f, "Unknown syntax: {name:?}")?;
+                match suggestion {
+                    Some(s) => write!(f, ", did you mean {s:?}?"),
+                    _ => Ok(()),
+                }
+            }
+            Self::Load(e) => write!(f, "Failed to load dump data: {e}"),
+            Self::Utf8(e) => write!(f, "Invalid UTF-8: {e}"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Parse(e) => Some(e),
+            Self::Load(e) => Some(e),
+            Self::Utf8(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<std::str::Utf8Error> for Error {
+    fn from(e: std::str::Utf8Error) -> Self {
+        Self::Utf8(e)
+    }
+}
+
+impl From<syntect::Error> for Error {
+    fn from(e: syntect::Error) -> Self {
+        Self::Parse(e)
+    }
+}
+
+impl From<syntect::parsing::ParsingError> for Error {
+    fn from(e: syntect::parsing::ParsingError) -> Self {
+        Self::Parse(e.into())
+    }
+}
+
+impl From<syntect::parsing::ScopeError> for Error {
+    fn from(e: syntect::parsing::ScopeError) -> Self {
+        Self::Parse(e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn source() {
+        let e = Error::from(io::Error::other("disk full"));
+        assert!(e.source().is_some());
+        assert_eq!(e.to_string(), "I/O error: disk full");
+
+        let e = Error::UnknownSyntax {
+            name: "tmol".into(),
+            suggestion: Some("toml".into()),
+        };
+        assert!(e.source().is_none());
+        assert_eq!(
+            e.to_string(),
+            r#"Unknown syntax: "tmol", did you mean "toml"?"#
+        );
+    }
+
+    #[test]
+    fn zlib_header() {
+        assert!(is_zlib(&[0x78, 0x9C, 0]));
+        assert!(is_zlib(&[0x78, 0xDA]));
+        assert!(!is_zlib(&[0x78, 0x00]));
+        assert!(!is_zlib(&[0x0E, 0, 0, 0]));
+    }
+}
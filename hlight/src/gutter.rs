@@ -0,0 +1,151 @@
+use getset::Getters;
+use std::borrow::Cow;
+use syntect::highlighting::{FontStyle, Highlighter, Style, Theme};
+
+/// Line numbers in front of every line, styled with the gutter colors of the theme.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{gen_syntax_highlight_string, HighLightRes, LineNumbers};
+///
+/// let numbers = LineNumbers::default().with_start(9).with_separator(" | ");
+/// let res = HighLightRes::default()
+///     .with_background(false)
+///     .with_line_numbers(Some(numbers));
+///
+/// let s = gen_syntax_highlight_string("toml", "a = 1\nb = 2\n", Some(&res))
+///     .expect("Failed to get highlighted text");
+///
+/// assert!(s.contains(" 9 | "));
+/// assert!(s.contains("10 | "));
+/// ```
+#[derive(Getters, Debug, Clone, PartialEq, Eq)]
+#[getset(get = "pub with_prefix")]
+pub struct LineNumbers {
+    start: usize,
+    width: usize,
+    separator: Cow<'static, str>,
+}
+
+impl Default for LineNumbers {
+    fn default() -> Self {
+        Self {
+            start: 1,
+            width: 0,
+            separator: Cow::from(" │ "),
+        }
+    }
+}
+
+impl LineNumbers {
+    /// Sets the number of the first line. The default is 1.
+    pub fn with_start(self, start: usize) -> Self {
+        Self { start, ..self }
+    }
+
+    /// Sets the minimum width of the line numbers, which are right-aligned.
+    ///
+    /// By default (0), it is as wide as the last line number. When the number of lines is not known in advance (e.g. [`gen_syntax_highlight_reader`](crate::gen_syntax_highlight_reader)), it is 4.
+    pub fn with_width(self, width: usize) -> Self {
+        Self { width, ..self }
+    }
+
+    /// Sets the text between the line number and the line. The default is `" │ "`.
+    pub fn with_separator<S>(self, separator: S) -> Self
+    where
+        S: Into<Cow<'static, str>>,
+    {
+        Self {
+            separator: separator.into(),
+            ..self
+        }
+    }
+}
+
+/// Generates the line number labels.
+pub(crate) struct Gutter {
+    start: usize,
+    width: usize,
+    separator: Cow<'static, str>,
+    style: Style,
+}
+
+impl Gutter {
+    /// `lines` is the number of the last line that is written out (1-based, without [`LineNumbers::with_start`]), if it is known in advance. Without a theme, the labels have the default style.
+    pub(crate) fn new(
+        numbers: &LineNumbers,
+        lines: Option<usize>,
+        theme: Option<&Theme>,
+    ) -> Self {
+        let last = numbers
+            .start
+            .saturating_add(lines.unwrap_or(1).saturating_sub(1));
+        let width = match (numbers.width, lines) {
+            (0, None) => 4,
+            (w, Some(_)) => w.max(digits(last)),
+            (w, None) => w,
+        };
+        Self {
+            start: numbers.start,
+            width,
+            separator: numbers.separator.clone(),
+            style: theme.map(gutter_style).unwrap_or_default(),
+        }
+    }
+
+    /// Returns the styled label of the line at `index` (0-based).
+    pub(crate) fn label(&self, index: usize) -> (Style, String) {
+        let number = self.start.saturating_add(index);
+        let label = format!("{number:>w$}{}", self.separator, w = self.width);
+        (self.style, label)
+    }
+
+    /// A context marker that is aligned with the line numbers.
+    pub(crate) fn marker(&self) -> String {
+        format!("{:>w$}\n", "⋮", w = self.width)
+    }
+}
+
+fn digits(n: usize) -> usize {
+    n.checked_ilog10().unwrap_or(0) as usize + 1
+}
+
+/// `gutter` and `gutterForeground` of the theme, falling back to the default colors.
+pub(crate) fn gutter_style(theme: &Theme) -> Style {
+    let default = Highlighter::new(theme).get_default();
+    Style {
+        foreground: theme
+            .settings
+            .gutter_foreground
+            .unwrap_or(default.foreground),
+        background: theme.settings.gutter.unwrap_or(default.background),
+        font_style: FontStyle::empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels() {
+        let numbers = LineNumbers::default().with_start(99);
+        let gutter = Gutter::new(&numbers, Some(3), None);
+
+        assert_eq!(gutter.label(0).1, " 99 │ ");
+        assert_eq!(gutter.label(1).1, "100 │ ");
+        assert_eq!(gutter.marker(), "  ⋮\n");
+
+        let numbers = numbers.with_width(2).with_separator(":");
+        let gutter = Gutter::new(&numbers, None, None);
+        assert_eq!(gutter.label(0).1, "99:");
+
+        let gutter = Gutter::new(&LineNumbers::default(), None, None);
+        assert_eq!(gutter.label(0).1, "   1 │ ");
+
+        let numbers = LineNumbers::default().with_start(usize::MAX);
+        let gutter = Gutter::new(&numbers, Some(2), None);
+        assert_eq!(gutter.label(1).1, format!("{} │ ", usize::MAX));
+    }
+}
@@ -0,0 +1,391 @@
+use crate::{
+    color::hex_color,
+    error::Result,
+    output::{render_highlight, with_output, Render},
+    resource::HighLightRes,
+    sanitize::Sanitizer,
+};
+use std::io::Write;
+pub use syntect::html::ClassStyle;
+use syntect::{
+    highlighting::{Color, Style, Theme},
+    html::{
+        css_for_theme_with_class_style, line_tokens_to_classed_spans,
+        start_highlighted_html_snippet, styled_line_to_highlighted_html,
+        IncludeBackground,
+    },
+    parsing::{ParseState, ScopeStack},
+    util::LinesWithEndings,
+};
+
+/// `<pre>` markup with inline `style` attributes.
+struct InlineHtml {
+    background: bool,
+    include_bg: IncludeBackground,
+}
+
+impl Render for InlineHtml {
+    /// Spans with another background than the theme (e.g. from an overlay) keep it, even if the background is disabled.
+    fn start(&mut self, theme: &Theme, out: &mut dyn Write) -> Result<()> {
+        let (pre, bg) = start_highlighted_html_snippet(theme);
+        self.include_bg = IncludeBackground::IfDifferent(bg);
+        match self.background {
+            true => Ok(out.write_all(pre.as_bytes())?),
+            _ => Ok(out.write_all(b"<pre>\n")?),
+        }
+    }
+
+    fn line(
+        &mut self,
+        ranges: &[(Style, &str)],
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let html = styled_line_to_highlighted_html(ranges, self.include_bg)?;
+        Ok(out.write_all(html.as_bytes())?)
+    }
+
+    /// The line is wrapped in a flex container, so that the background fills the whole width of `<pre>`.
+    fn emphasized_line(
+        &mut self,
+        ranges: &[(Style, &str)],
+        background: Color,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        write!(
+            out,
+            r#"<span style="display:flex;background-color:{};">"#,
+            hex_color(background)
+        )?;
+        self.line(ranges, out)?;
+        Ok(out.write_all(b"</span>")?)
+    }
+
+    fn finish(&mut self, out: &mut dyn Write) -> Result<()> {
+        Ok(out.write_all(b"</pre>\n")?)
+    }
+
+    fn plain(&mut self, contents: &str, out: &mut dyn Write) -> Result<()> {
+        write_plain_html(contents, out)
+    }
+}
+
+fn write_plain_html(contents: &str, out: &mut dyn Write) -> Result<()> {
+    out.write_all(b"<pre>\n")?;
+    out.write_all(escape_html(contents).as_bytes())?;
+    Ok(out.write_all(b"</pre>\n")?)
+}
+
+/// The prefix that `class_style` adds to every class name.
+fn class_prefix(class_style: ClassStyle) -> &'static str {
+    match class_style {
+        ClassStyle::SpacedPrefixed { prefix } => prefix,
+        _ => "",
+    }
+}
+
+/// Escapes `<`, `>`, `&`, `'` and `"` so that the text can be embedded in HTML.
+///
+/// # Example
+///
+/// ```
+/// use hlight::html::escape_html;
+///
+/// assert_eq!(escape_html(r#"a = "<b>""#), "a = &quot;&lt;b&gt;&quot;");
+/// ```
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '\'' => escaped.push_str("&#39;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes syntax-highlighted code as a self-contained `<pre>` block with inline styles to either standard output or a provided writer.
+///
+/// It takes the same parameters as [`gen_syntax_highlight`](crate::gen_syntax_highlight). If the background of `style` is enabled, the `<pre>` element gets the background color of the theme.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{gen_html_highlight, HighLightRes};
+///
+/// let s = "
+/// [main]
+/// enabled = false
+/// ";
+///
+/// let res = HighLightRes::default().with_background(false);
+/// let mut html = Vec::new();
+///
+/// gen_html_highlight("toml", s, Some(&res), Some(&mut html))
+///     .expect("Failed to get highlighted html");
+///
+/// let html = String::from_utf8(html).expect("Invalid UTF-8");
+/// assert!(html.starts_with("<pre>"));
+/// assert!(html.contains(r#"<span style="color:#"#));
+/// ```
+pub fn gen_html_highlight(
+    dst_fmt: &str,
+    contents: &str,
+    style: Option<&HighLightRes>,
+    writer: Option<&mut dyn Write>,
+) -> Result<()> {
+    let mut html = InlineHtml {
+        background: style.is_some_and(|s| *s.get_background()),
+        include_bg: IncludeBackground::No,
+    };
+    render_highlight(dst_fmt, contents, style, writer, &mut html)
+}
+
+/// Writes syntax-highlighted code as a `<pre class="code">` block to either standard output or a provided writer.
+///
+/// Unlike [`gen_html_highlight`], the tokens are emitted as `<span class="...">` elements derived from their scopes (e.g. `source.toml` becomes `class="source toml"`), so the colors come from a stylesheet generated by [`css_for_theme`]. Switching themes is then just a matter of swapping the CSS.
+///
+/// With `ClassStyle::SpacedPrefixed { prefix }`, every class (including `code`) is prefixed.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{
+///     html::{gen_classed_html_highlight, ClassStyle},
+///     HighLightRes,
+/// };
+///
+/// let s = "
+/// [main]
+/// enabled = false
+/// ";
+///
+/// let res = HighLightRes::default();
+/// let mut html = Vec::new();
+///
+/// gen_classed_html_highlight(
+///     "toml",
+///     s,
+///     Some(&res),
+///     ClassStyle::Spaced,
+///     Some(&mut html),
+/// )
+/// .expect("Failed to get highlighted html");
+///
+/// let html = String::from_utf8(html).expect("Invalid UTF-8");
+/// assert!(html.starts_with(r#"<pre class="code">"#));
+/// ```
+pub fn gen_classed_html_highlight(
+    dst_fmt: &str,
+    contents: &str,
+    style: Option<&HighLightRes>,
+    class_style: ClassStyle,
+    writer: Option<&mut dyn Write>,
+) -> Result<()> {
+    with_output(writer, |out| {
+        let hl_res = match style {
+            Some(s) if s.get_name() != "None" => s,
+            _ => {
+                let contents = Sanitizer::new(style).text(contents);
+                return write_plain_html(&contents, out);
+            }
+        };
+        let syntax_set = hl_res.get_syntax_set();
+        let syntax = *hl_res.resolve_syntax(dst_fmt, contents)?.get_syntax();
+
+        log::debug!("syntax:{}", syntax.name);
+
+        let mut state = ParseState::new(syntax);
+        let mut stack = ScopeStack::new();
+        let mut open_spans = 0;
+        let sanitizer = Sanitizer::new(style);
+
+        writeln!(out, r#"<pre class="{}code">"#, class_prefix(class_style))?;
+
+        for line in LinesWithEndings::from(contents) {
+            let line = sanitizer.text(line);
+            let ops = state.parse_line(&line, syntax_set)?;
+            let (html, delta) = line_tokens_to_classed_spans(
+                &line,
+                &ops,
+                class_style,
+                &mut stack,
+            )?;
+
+            open_spans += delta;
+            out.write_all(html.as_bytes())?;
+        }
+        for _ in 0..open_spans {
+            out.write_all(b"</span>")?;
+        }
+        Ok(out.write_all(b"</pre>\n")?)
+    })
+}
+
+/// Generates a CSS stylesheet from `theme` for the output of [`gen_classed_html_highlight`].
+///
+/// `class_style` must be the same as the one used for generating the HTML.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{
+///     html::{css_for_theme, ClassStyle},
+///     HighLightRes,
+/// };
+///
+/// let set = HighLightRes::static_theme_set();
+///
+/// for (name, theme) in &set.themes {
+///     let css = css_for_theme(theme, ClassStyle::Spaced)
+///         .expect("Failed to generate css");
+///     println!("/* {name} */\n{css}")
+/// }
+/// ```
+pub fn css_for_theme(theme: &Theme, class_style: ClassStyle) -> Result<String> {
+    Ok(css_for_theme_with_class_style(theme, class_style)?)
+}
+
+impl<'name> HighLightRes<'name> {
+    /// Generates a CSS stylesheet from the current theme.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::{html::ClassStyle, theme::theme_ayu_dark, HighLightRes};
+    ///
+    /// let mut res = HighLightRes::default();
+    /// *res.get_name_mut() = theme_ayu_dark();
+    ///
+    /// let css = res.css(ClassStyle::Spaced).expect("Failed to generate css");
+    /// assert!(css.contains(".code {"));
+    /// ```
+    pub fn css(&self, class_style: ClassStyle) -> Result<String> {
+        let theme = self.try_theme_once()?;
+        css_for_theme(theme, class_style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const S: &str = r#"
+    [main]
+    enabled = false
+    "<a>" = '&'
+    "#;
+
+    fn to_html(res: Option<&HighLightRes>) -> Result<String> {
+        let mut v = Vec::new();
+        gen_html_highlight("toml", S, res, Some(&mut v))?;
+        Ok(String::from_utf8(v).expect("Invalid UTF-8"))
+    }
+
+    #[test]
+    fn inline_html() -> Result<()> {
+        let res = HighLightRes::default().with_background(false);
+        let html = to_html(Some(&res))?;
+
+        assert!(html.starts_with("<pre>\n"));
+        assert!(html.ends_with("</pre>\n"));
+        assert!(html.contains("&lt;a&gt;"));
+        assert!(!html.contains("background-color"));
+        Ok(())
+    }
+
+    #[test]
+    fn inline_html_with_background() -> Result<()> {
+        let html = to_html(Some(&HighLightRes::default()))?;
+        assert!(html.starts_with(r#"<pre style="background-color:#"#));
+        Ok(())
+    }
+
+    #[test]
+    fn classed_html() -> Result<()> {
+        let res = HighLightRes::default();
+        let mut v = Vec::new();
+        let class_style = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+        gen_classed_html_highlight(
+            "toml",
+            S,
+            Some(&res),
+            class_style,
+            Some(&mut v),
+        )?;
+
+        let html = String::from_utf8(v).expect("Invalid UTF-8");
+        assert!(html.starts_with(r#"<pre class="hl-code">"#));
+        assert!(html.contains(r#"<span class="hl-source hl-toml">"#));
+        assert!(!html.contains("style="));
+        assert_eq!(
+            html.matches("<span").count(),
+            html.matches("</span>").count()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn css_for_static_themes() -> Result<()> {
+        let class_style = ClassStyle::Spaced;
+        for theme in HighLightRes::static_theme_set().themes.values() {
+            let css = css_for_theme(theme, class_style)?;
+            assert!(css.contains(".code {"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn line_numbers() -> Result<()> {
+        let res = HighLightRes::default()
+            .with_line_numbers(Some(crate::LineNumbers::default()));
+        let html = to_html(Some(&res))?;
+
+        assert!(html.contains(">1 │ \n"));
+        assert!(html.contains(">5 │     </span></pre>"));
+        Ok(())
+    }
+
+    #[test]
+    fn emphasized_lines() -> Result<()> {
+        let res = HighLightRes::default().with_emphasized_lines([3]);
+        let html = to_html(Some(&res))?;
+
+        let emphasized =
+            r#"<span style="display:flex;background-color:#333333;">"#;
+        assert_eq!(html.matches(emphasized).count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn overlays() -> Result<()> {
+        use crate::StyleModifier;
+
+        let marked = StyleModifier {
+            background: Some(Color::WHITE),
+            ..Default::default()
+        };
+        // "main"
+        let res = HighLightRes::default()
+            .with_background(false)
+            .with_overlays([(6..10, marked)]);
+        let html = to_html(Some(&res))?;
+
+        assert_eq!(html.matches("background-color:#ffffff;").count(), 1);
+        assert!(html.contains(
+            r#"<span style="background-color:#ffffff;color:#a6e22e;">main</span>"#
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn plain_html() -> Result<()> {
+        let html = to_html(None)?;
+        assert!(html.contains("&quot;&lt;a&gt;&quot; = &#39;&amp;&#39;"));
+        assert!(!html.contains("<span"));
+        Ok(())
+    }
+}
@@ -46,10 +46,14 @@
 //! <hr />
 //!
 //! ```no_run
+//! # use hlight::{gen_syntax_highlight, HighLightRes};
+//! # let s = "[main]\nenabled = false\n";
+//! # let mut res = HighLightRes::default();
 //! use std::borrow::Cow;
 //! *res.get_name_mut() = Cow::from("OneHalfLight");
 //!
 //! gen_syntax_highlight("toml", s, Some(&res), None)?;
+//! # Ok::<(), hlight::Error>(())
 //! ```
 //!
 //! output:
@@ -61,18 +65,58 @@
 //!     <span style="color:#E45649">float</span><span style="color:#383A42"> = </span><span style="color:#C18401">nan</span>
 //! </pre>
 //!
+//! ### html
+//!
+//! ```no_run
+//! # use hlight::HighLightRes;
+//! # let s = "[main]\nenabled = false\n";
+//! # let res = HighLightRes::default();
+//! use hlight::gen_html_highlight;
+//!
+//! // <pre style="background-color:#..;">\n<span style="color:#..;">[</span>...</pre>
+//! gen_html_highlight("toml", s, Some(&res), None)?;
+//! # Ok::<(), hlight::Error>(())
+//! ```
+//!
 //! ### write to file
 //!
-//! ```rust
+//! ```no_run
+//! # use hlight::{gen_syntax_highlight, HighLightRes};
+//! # let s = "[main]\nenabled = false\n";
+//! # let res = HighLightRes::default();
 //! use std::fs::File;
 //!
 //! let mut file = File::create("test.txt").expect("Failed to create test.txt");
 //! gen_syntax_highlight("toml", s, Some(&res), Some(&mut file))
 //!     .expect("Unable to write syntax-highlighted text to file.")
 //! ```
+pub mod color;
+mod dump;
+mod error;
+mod gutter;
+pub mod html;
 mod output;
+mod overlay;
+mod range;
 mod resource;
+mod sanitize;
+pub mod svg;
 pub mod syntax;
 pub mod theme;
+pub mod token;
 
-pub use crate::{output::gen_syntax_highlight, resource::HighLightRes};
+pub use crate::{
+    error::{Error, LoadError, Result},
+    gutter::LineNumbers,
+    html::gen_html_highlight,
+    output::{
+        gen_syntax_highlight, gen_syntax_highlight_bytes,
+        gen_syntax_highlight_fmt, gen_syntax_highlight_reader,
+        gen_syntax_highlight_string, Highlighted, InvalidUtf8,
+    },
+    overlay::{FontStyle, StyleModifier},
+    range::LineRange,
+    resource::HighLightRes,
+    sanitize::{ControlChars, InvisibleChars},
+    svg::gen_svg_highlight,
+};
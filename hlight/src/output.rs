@@ -1,13 +1,282 @@
-use crate::{resource::HighLightRes, syntax::match_static_syntax};
-use std::io::{self, BufWriter, Write};
+use crate::{
+    color::{blend_fg_color, ColorDepth},
+    error::Result,
+    gutter::{gutter_style, Gutter},
+    range::LineRange,
+    resource::HighLightRes,
+    sanitize::Sanitizer,
+    token::Tokens,
+};
+use std::{
+    borrow::Cow,
+    collections::BTreeSet,
+    fmt,
+    io::{self, BufRead, BufWriter, IsTerminal, Write},
+    ops::ControlFlow,
+};
 use syntect::{
-    easy::HighlightLines,
-    parsing::SyntaxSet,
-    util::{as_24_bit_terminal_escaped, LinesWithEndings},
+    highlighting::{Color, FontStyle, Style, Theme},
+    util::LinesWithEndings,
 };
 
+/// Turns highlighted lines into a concrete output format (e.g. ANSI escapes or HTML).
+///
+/// The line loop in [`write_highlight_line`] is shared by every format, so a renderer only has to care about how a single line looks.
+pub(crate) trait Render {
+    /// Called once before the first line.
+    fn start(&mut self, _theme: &Theme, _out: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+
+    /// Writes one highlighted line (including its line ending, if any).
+    fn line(
+        &mut self,
+        ranges: &[(Style, &str)],
+        out: &mut dyn Write,
+    ) -> Result<()>;
+
+    /// Called once after the last line.
+    fn finish(&mut self, _out: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+
+    /// Writes a line that is emphasized with a full-width `background`.
+    ///
+    /// By default, the background of every span is replaced.
+    fn emphasized_line(
+        &mut self,
+        ranges: &[(Style, &str)],
+        background: Color,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let ranges = ranges
+            .iter()
+            .map(|&(style, s)| {
+                (
+                    Style {
+                        background,
+                        ..style
+                    },
+                    s,
+                )
+            })
+            .collect::<Vec<_>>();
+        self.line(&ranges, out)
+    }
+
+    /// Writes `contents` without any highlighting. It is used when the theme name is "None".
+    fn plain(&mut self, contents: &str, out: &mut dyn Write) -> Result<()> {
+        Ok(out.write_all(contents.as_bytes())?)
+    }
+}
+
+/// Terminal escapes with the color depth of [`HighLightRes`].
+struct Ansi {
+    background: bool,
+    /// The background of the theme. If the background is disabled, only spans with another background (e.g. from an overlay) get one.
+    theme_background: Option<Color>,
+    depth: ColorDepth,
+    /// Whether bold/italic/underline are rendered.
+    font_style: bool,
+    /// The font style of the previous token, so that only changes are emitted.
+    prev_font_style: FontStyle,
+    sanitizer: Sanitizer,
+}
+
+/// (flag, SGR code to enable it, SGR code to disable it)
+const FONT_STYLE_SGR: [(FontStyle, &str, &str); 3] = [
+    (FontStyle::BOLD, "1", "22"),
+    (FontStyle::ITALIC, "3", "23"),
+    (FontStyle::UNDERLINE, "4", "24"),
+];
+
+impl Ansi {
+    fn new(style: Option<&HighLightRes>, to_stdout: bool) -> Self {
+        let is_terminal = to_stdout && io::stdout().is_terminal();
+        let depth = style
+            .map(|s| *s.get_color_depth())
+            .unwrap_or_default()
+            .resolve(is_terminal);
+
+        Self {
+            background: style.is_some_and(|s| *s.get_background()),
+            theme_background: None,
+            depth,
+            font_style: style.is_some_and(|s| *s.get_font_style()),
+            prev_font_style: FontStyle::empty(),
+            sanitizer: Sanitizer::new(style),
+        }
+    }
+
+    fn push_font_style_escape(
+        &mut self,
+        s: &mut String,
+        font_style: FontStyle,
+    ) {
+        for (flag, on, off) in FONT_STYLE_SGR {
+            let code = match (
+                self.prev_font_style.contains(flag),
+                font_style.contains(flag),
+            ) {
+                (false, true) => on,
+                (true, false) => off,
+                _ => continue,
+            };
+            s.push_str("\x1B[");
+            s.push_str(code);
+            s.push('m');
+        }
+        self.prev_font_style = font_style;
+    }
+}
+
+impl Ansi {
+    /// Writes `bytes` as is, without any styling.
+    fn raw(&mut self, bytes: &[u8], out: &mut dyn Write) -> Result<()> {
+        if self.depth != ColorDepth::NoColor {
+            out.write_all(b"\x1B[0m")?;
+        }
+        self.prev_font_style = FontStyle::empty();
+        let bytes = self.sanitizer.invalid_bytes(bytes);
+        Ok(out.write_all(&bytes)?)
+    }
+
+    /// Writes a highlighted line that was decoded lossily from `bytes`, but with the original invalid bytes instead of the replacement characters.
+    fn line_with_invalid_bytes(
+        &mut self,
+        ranges: &[(Style, &str)],
+        bytes: &[u8],
+        mut label: Option<(Style, String)>,
+        lines: &LineRenderer,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        // (offset of the replacement character in the decoded line, invalid bytes)
+        let mut invalid = Vec::new();
+        let mut offset = 0;
+        for chunk in bytes.utf8_chunks() {
+            offset += chunk.valid().len();
+            if !chunk.invalid().is_empty() {
+                invalid.push((offset, chunk.invalid()));
+                offset += char::REPLACEMENT_CHARACTER.len_utf8();
+            }
+        }
+
+        let mut invalid = invalid.into_iter().peekable();
+        let mut spans = Vec::new();
+        let mut start = 0;
+
+        for &(style, text) in ranges {
+            let end = start + text.len();
+            let mut pos = start;
+
+            while let Some(&(at, raw)) =
+                invalid.peek().filter(|(at, _)| *at < end)
+            {
+                spans.push((style, &text[pos - start..at - start]));
+                lines.write(self, false, label.take(), &spans, out)?;
+                self.raw(raw, out)?;
+
+                spans.clear();
+                pos = at + char::REPLACEMENT_CHARACTER.len_utf8();
+                invalid.next();
+            }
+            spans.push((style, &text[pos - start..]));
+            start = end;
+        }
+        lines.write(self, false, label, &spans, out)
+    }
+}
+
+impl Render for Ansi {
+    fn start(&mut self, theme: &Theme, _out: &mut dyn Write) -> Result<()> {
+        self.theme_background = theme.settings.background;
+        Ok(())
+    }
+
+    fn line(
+        &mut self,
+        ranges: &[(Style, &str)],
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let mut s = String::new();
+        for (style, text) in ranges {
+            let other_background = !self.background
+                && self.depth != ColorDepth::NoColor
+                && self
+                    .theme_background
+                    .is_some_and(|bg| bg != style.background);
+            if self.background || other_background {
+                self.depth.push_escape(&mut s, style.background, true);
+            }
+            let fg = blend_fg_color(style.foreground, style.background);
+            self.depth.push_escape(&mut s, fg, false);
+            if self.font_style && self.depth != ColorDepth::NoColor {
+                self.push_font_style_escape(&mut s, style.font_style);
+            }
+            s.push_str(text);
+            if other_background {
+                s.push_str("\x1B[49m");
+            }
+        }
+        Ok(out.write_all(s.as_bytes())?)
+    }
+
+    /// The background is always written, even if it is disabled. `\x1B[K` fills the rest of the line with it.
+    fn emphasized_line(
+        &mut self,
+        ranges: &[(Style, &str)],
+        background: Color,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let mut ranges = ranges
+            .iter()
+            .map(|&(style, s)| {
+                (
+                    Style {
+                        background,
+                        ..style
+                    },
+                    s,
+                )
+            })
+            .collect::<Vec<_>>();
+        let line_ending = match ranges.last_mut() {
+            Some((_, s)) => {
+                let text = s.trim_end_matches(['\n', '\r']);
+                let line_ending = &s[text.len()..];
+                *s = text;
+                line_ending
+            }
+            _ => "",
+        };
+
+        let prev_background = std::mem::replace(&mut self.background, true);
+        self.line(&ranges, out)?;
+        self.background = prev_background;
+
+        if self.depth != ColorDepth::NoColor {
+            out.write_all(b"\x1B[K")?;
+            if !self.background {
+                out.write_all(b"\x1B[49m")?;
+            }
+        }
+        Ok(out.write_all(line_ending.as_bytes())?)
+    }
+
+    fn finish(&mut self, out: &mut dyn Write) -> Result<()> {
+        match self.depth {
+            ColorDepth::NoColor => Ok(()),
+            _ => Ok(out.write_all(b"\x1B[0m")?),
+        }
+    }
+}
+
 /// Prints syntax-highlighted code to either standard output or a provided writer, using the selected syntax highlighting style to highlight the code beforehand.
 ///
+/// The syntax is resolved by the [`SyntaxResolver`](crate::syntax::SyntaxResolver) of `style`, which falls back to plain text for an unknown format by default. [`HighLightRes::resolve_syntax`] reports which syntax is used and why.
+///
+/// With [`ColorDepth::Auto`](crate::color::ColorDepth::Auto), the escape sequences depend on whether standard output is a terminal and on `NO_COLOR`, `CLICOLOR_FORCE` and `COLORTERM`.
+///
 /// # Example
 ///
 /// ```no_run
@@ -31,71 +300,658 @@ pub fn gen_syntax_highlight(
     contents: &str,
     style: Option<&HighLightRes>,
     writer: Option<&mut dyn Write>,
-) -> io::Result<()> {
-    let mut stdout = BufWriter::new(std::io::stdout());
+) -> Result<()> {
+    let mut ansi = Ansi::new(style, writer.is_none());
+    render_highlight(dst_fmt, contents, style, writer, &mut ansi)
+}
 
-    let out = match writer {
-        Some(w) => {
-            drop(stdout);
-            w
-        }
-        _ => &mut stdout as &mut dyn Write,
+/// How [`gen_syntax_highlight_bytes`] handles input that is not valid UTF-8, e.g. Latin-1 files or stray binary bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidUtf8 {
+    /// Replaces invalid sequences with U+FFFD (�).
+    #[default]
+    Lossy,
+    /// Highlights the valid text, and writes the invalid bytes out unchanged, without any styling.
+    Passthrough,
+    /// Returns [`Error::Utf8`](crate::Error::Utf8).
+    Error,
+}
+
+/// Highlights `contents` like [`gen_syntax_highlight`], but accepts bytes that may not be valid UTF-8.
+///
+/// Invalid UTF-8 is handled according to [`HighLightRes::get_invalid_utf8`]. Without `style`, [`InvalidUtf8::Lossy`] is used.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{gen_syntax_highlight_bytes, HighLightRes, InvalidUtf8};
+///
+/// // "caf\xe9" is Latin-1
+/// let s = b"name = \"caf\xe9\"\n";
+/// let res = HighLightRes::default().with_invalid_utf8(InvalidUtf8::Passthrough);
+/// let mut v = Vec::new();
+///
+/// gen_syntax_highlight_bytes("toml", s, Some(&res), Some(&mut v))
+///     .expect("Failed to get syntax highlighting");
+/// assert!(v.contains(&0xE9));
+///
+/// let res = res.with_invalid_utf8(InvalidUtf8::Error);
+/// assert!(gen_syntax_highlight_bytes("toml", s, Some(&res), None).is_err());
+/// ```
+pub fn gen_syntax_highlight_bytes(
+    dst_fmt: &str,
+    contents: &[u8],
+    style: Option<&HighLightRes>,
+    writer: Option<&mut dyn Write>,
+) -> Result<()> {
+    let err = match std::str::from_utf8(contents) {
+        Ok(s) => return gen_syntax_highlight(dst_fmt, s, style, writer),
+        Err(e) => e,
     };
+    log::debug!("{err}");
 
-    let hl_res = match style {
-        Some(s) if s.get_name() != "None" => s,
-        _ => {
-            out.write_all(contents.as_bytes())?;
-            return out.flush();
+    match style.map(|s| *s.get_invalid_utf8()).unwrap_or_default() {
+        InvalidUtf8::Lossy => {
+            let s = String::from_utf8_lossy(contents);
+            gen_syntax_highlight(dst_fmt, &s, style, writer)
         }
-    };
-    let syntax_set = hl_res.get_syntax_set();
+        InvalidUtf8::Error => Err(err.into()),
+        InvalidUtf8::Passthrough => {
+            let mut ansi = Ansi::new(style, writer.is_none());
 
-    log::debug!("About to Load the SyntaxSet and ThemeSet");
+            with_output(writer, |out| {
+                let hl_res = match style {
+                    Some(s) if s.get_name() != "None" => s,
+                    _ => return write_plain_bytes(contents, style, out),
+                };
+                let syntax_set = hl_res.get_syntax_set();
+                let decoded = String::from_utf8_lossy(contents);
+                let mut highlight =
+                    hl_res.highlight_lines(dst_fmt, &decoded)?;
+                let theme = hl_res.try_theme_once()?;
+                let count = split_lines(contents).count();
+                let mut lines =
+                    LineRenderer::new(style, Some(theme), Some(count));
+                let mut overlays = hl_res.overlays(theme);
 
-    let syntax = match_static_syntax(syntax_set, dst_fmt);
+                ansi.start(theme, out)?;
+                for line in split_lines(contents) {
+                    let decoded = String::from_utf8_lossy(line);
+                    let ranges = overlays
+                        .apply(highlight.highlight_line(&decoded, syntax_set)?);
+                    let (marker, label) = match lines.next_line() {
+                        NextLine::Hidden => continue,
+                        NextLine::Visible { marker, label } => (marker, label),
+                        NextLine::End { marker } => {
+                            if marker {
+                                lines.write_marker(&mut ansi, out)?;
+                            }
+                            break;
+                        }
+                    };
+                    match decoded {
+                        Cow::Borrowed(_) => lines
+                            .write(&mut ansi, marker, label, &ranges, out)?,
+                        Cow::Owned(_) => {
+                            if marker {
+                                lines.write_marker(&mut ansi, out)?;
+                            }
+                            ansi.line_with_invalid_bytes(
+                                &ranges, line, label, &lines, out,
+                            )?
+                        }
+                    }
+                }
+                ansi.finish(out)
+            })
+        }
+    }
+}
 
-    log::trace!("ext: {:?}", syntax.file_extensions);
-    log::debug!("syntax:{}", syntax.name);
+/// Highlights lines read from `reader` as they come, and writes them to either standard output or a provided writer.
+///
+/// Unlike [`gen_syntax_highlight`], the whole document is never held in memory: only the current line is buffered, while the parse state is kept across lines. This makes it suitable for large files and stdin pipelines.
+///
+/// Returns [`Error::Io`](crate::Error::Io) if a line is not valid UTF-8.
+///
+/// # Example
+///
+/// ```no_run
+/// use hlight::{gen_syntax_highlight_reader, HighLightRes};
+/// use std::io::stdin;
+///
+/// let res = HighLightRes::default().with_background(false);
+///
+/// // cat Cargo.toml | this-program
+/// gen_syntax_highlight_reader("toml", &mut stdin().lock(), Some(&res), None)
+///     .expect("Failed to get syntax highlighting");
+/// ```
+pub fn gen_syntax_highlight_reader(
+    dst_fmt: &str,
+    reader: &mut dyn BufRead,
+    style: Option<&HighLightRes>,
+    writer: Option<&mut dyn Write>,
+) -> Result<()> {
+    let mut ansi = Ansi::new(style, writer.is_none());
 
-    let highlight = HighlightLines::new(syntax, hl_res.set_theme_once());
+    with_output(writer, |out| {
+        let hl_res = match style {
+            Some(s) if s.get_name() != "None" => s,
+            _ => {
+                let lines = LineRenderer::new(style, None, None);
+                return copy_plain_lines(reader, lines, out);
+            }
+        };
+        let syntax_set = hl_res.get_syntax_set();
+        // Only the buffered lines are available for sniffing.
+        let buffered = reader.fill_buf()?;
+        let buffered = match buffered.iter().rposition(|&b| b == b'\n') {
+            Some(i) => &buffered[..=i],
+            _ => buffered,
+        };
+        let buffered = String::from_utf8_lossy(buffered).into_owned();
+        let mut highlight = hl_res.highlight_lines(dst_fmt, &buffered)?;
+        let theme = hl_res.try_theme_once()?;
+        let mut lines = LineRenderer::new(style, Some(theme), None);
+        let mut overlays = hl_res.overlays(theme);
+        let mut line = String::new();
 
-    write_highlight_line(
-        contents,
-        highlight,
-        syntax_set,
-        *hl_res.get_background(),
-        out,
-    )?;
-    out.flush()?;
+        ansi.start(theme, out)?;
+        while reader.read_line(&mut line)? != 0 {
+            let ranges =
+                overlays.apply(highlight.highlight_line(&line, syntax_set)?);
+            if !lines.line(&mut ansi, &ranges, out)? {
+                break;
+            }
+            line.clear();
+        }
+        ansi.finish(out)
+    })
+}
 
-    log::debug!("Output complete");
-    Ok(())
+/// Highlights `contents` like [`gen_syntax_highlight`], but writes to a [`fmt::Write`] (e.g. `String`, `fmt::Formatter`) instead of an [`io::Write`].
+///
+/// # Example
+///
+/// ```
+/// use hlight::{gen_syntax_highlight_fmt, HighLightRes};
+/// use std::fmt::Write;
+///
+/// let res = HighLightRes::default();
+/// let mut s = String::from("config:\n");
+///
+/// gen_syntax_highlight_fmt("toml", "enabled = true\n", Some(&res), &mut s)
+///     .expect("Failed to get highlighted text");
+/// writeln!(s, "---").expect("Failed to write to string");
+/// ```
+pub fn gen_syntax_highlight_fmt(
+    dst_fmt: &str,
+    contents: &str,
+    style: Option<&HighLightRes>,
+    writer: &mut dyn fmt::Write,
+) -> Result<()> {
+    let mut out = FmtWriter(writer);
+    gen_syntax_highlight(dst_fmt, contents, style, Some(&mut out))
 }
 
-/// Performs the actual highlighting of lines of code, and writes the highlighted output to the specified output stream.
+/// Highlights `contents` like [`gen_syntax_highlight`], and returns the result as a `String`.
 ///
-/// The function loops through each line of the `contents` parameter, uses the `highlight_line` method to highlight each line, and gets the escaped 24-bit terminal format of the highlighted ranges using the `as_24_bit_terminal_escaped` function.
+/// # Example
 ///
-/// Finally, it writes the escaped 24-bit terminal format to the output.
-fn write_highlight_line(
+/// ```
+/// use hlight::{gen_syntax_highlight_string, HighLightRes};
+///
+/// let res = HighLightRes::default();
+/// let s = gen_syntax_highlight_string("toml", "enabled = true\n", Some(&res))
+///     .expect("Failed to get highlighted text");
+///
+/// assert!(s.contains("enabled"));
+/// ```
+pub fn gen_syntax_highlight_string(
+    dst_fmt: &str,
     contents: &str,
-    mut highlight: HighlightLines,
-    syntax_set: &SyntaxSet,
-    background: bool,
-    out: &mut dyn Write,
-) -> io::Result<()> {
-    for line in LinesWithEndings::from(contents) {
-        let ranges = highlight
-            .highlight_line(line, syntax_set)
+    style: Option<&HighLightRes>,
+) -> Result<String> {
+    let mut s = String::with_capacity(contents.len() * 2);
+    gen_syntax_highlight_fmt(dst_fmt, contents, style, &mut s)?;
+    Ok(s)
+}
+
+/// Syntax-highlighted text that is only rendered when it is displayed, so it can be used with `format!`, `println!`, `log` macros, etc.
+///
+/// It is rendered like [`gen_syntax_highlight`]. Since a formatter is not a terminal, [`ColorDepth::Auto`] resolves to no colors unless `CLICOLOR_FORCE` is set.
+///
+/// Errors (e.g. an unknown theme name) are logged and turned into [`fmt::Error`].
+///
+/// # Example
+///
+/// ```
+/// use hlight::{HighLightRes, Highlighted};
+///
+/// let res = HighLightRes::default().with_background(false);
+/// let toml = Highlighted::new("toml", "enabled = true\n", &res);
+///
+/// println!("config:\n{toml}");
+/// let s = format!("{}", res.highlight("yaml", "enabled: true\n"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Highlighted<'a> {
+    dst_fmt: &'a str,
+    contents: &'a str,
+    res: &'a HighLightRes<'a>,
+}
+
+impl<'a> Highlighted<'a> {
+    pub fn new(
+        dst_fmt: &'a str,
+        contents: &'a str,
+        res: &'a HighLightRes,
+    ) -> Self {
+        Self {
+            dst_fmt,
+            contents,
+            res,
+        }
+    }
+}
+
+impl fmt::Display for Highlighted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        gen_syntax_highlight_fmt(self.dst_fmt, self.contents, Some(self.res), f)
             .map_err(|e| {
-                io::Error::new(io::ErrorKind::InvalidData, e.to_string())
-            })?;
+                log::error!("{e}");
+                fmt::Error
+            })
+    }
+}
+
+impl<'name> HighLightRes<'name> {
+    /// Wraps `contents` in [`Highlighted`], which is highlighted with this resource when it is displayed.
+    pub fn highlight<'a>(
+        &'a self,
+        dst_fmt: &'a str,
+        contents: &'a str,
+    ) -> Highlighted<'a> {
+        Highlighted::new(dst_fmt, contents, self)
+    }
+}
 
-        let escaped = as_24_bit_terminal_escaped(&ranges[..], background);
-        out.write_all(escaped.as_bytes())?
+/// Adapts a [`fmt::Write`] to an [`io::Write`].
+///
+/// The renderers only write complete UTF-8 strings, so every chunk is expected to be valid UTF-8.
+pub(crate) struct FmtWriter<'w>(pub(crate) &'w mut dyn fmt::Write);
+
+impl io::Write for FmtWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let s = std::str::from_utf8(buf)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.0.write_str(s).map_err(io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `f` with either the provided writer or a buffered standard output, and flushes it afterwards.
+pub(crate) fn with_output<F>(writer: Option<&mut dyn Write>, f: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    match writer {
+        Some(w) => {
+            f(w)?;
+            Ok(w.flush()?)
+        }
+        _ => {
+            let mut stdout = BufWriter::new(io::stdout());
+            f(&mut stdout)?;
+            Ok(stdout.flush()?)
+        }
+    }
+}
+
+/// Resolves the syntax and theme, then renders `contents` with `render` to either standard output or the provided writer.
+pub(crate) fn render_highlight(
+    dst_fmt: &str,
+    contents: &str,
+    style: Option<&HighLightRes>,
+    writer: Option<&mut dyn Write>,
+    render: &mut dyn Render,
+) -> Result<()> {
+    with_output(writer, |out| {
+        let hl_res = match style {
+            Some(s) if s.get_name() != "None" => s,
+            _ => {
+                let count = LinesWithEndings::from(contents).count();
+                let mut lines = LineRenderer::new(style, None, Some(count));
+                return render.plain(&lines.plain(contents), out);
+            }
+        };
+        log::debug!("About to Load the SyntaxSet and ThemeSet");
+
+        let tokens = hl_res.tokens(dst_fmt, contents)?;
+        let theme = hl_res.try_theme_once()?;
+
+        let count = LinesWithEndings::from(contents).count();
+        let lines = LineRenderer::new(style, Some(theme), Some(count));
+
+        render.start(theme, out)?;
+        write_highlight_line(tokens, render, lines, out)?;
+        render.finish(out)?;
+
+        log::debug!("Output complete");
+        Ok(())
+    })
+}
+
+/// Performs the actual highlighting of lines of code, and writes the highlighted output to the specified output stream.
+///
+/// The function loops through the highlighted lines of [`Tokens`], and passes the highlighted ranges of each line to the renderer.
+fn write_highlight_line(
+    tokens: Tokens,
+    render: &mut dyn Render,
+    mut lines: LineRenderer,
+    out: &mut dyn Write,
+) -> Result<()> {
+    for ranges in tokens {
+        if !lines.line(render, &ranges?, out)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Prepares highlighted lines for a renderer: selects the lines in [`LineRange`], sanitizes the text (see [`Sanitizer`]) and prepends the line numbers.
+pub(crate) struct LineRenderer {
+    sanitizer: Sanitizer,
+    gutter: Option<Gutter>,
+    line_range: Option<LineRange>,
+    marker_style: Style,
+    /// The line numbers (1-based) to emphasize, and the background color.
+    emphasis: Option<(BTreeSet<usize>, Color)>,
+    /// The index of the next line (0-based).
+    index: usize,
+    /// Whether the end of the line range has been reached.
+    ended: bool,
+}
+
+/// Where the next line is relative to [`LineRange`].
+pub(crate) enum NextLine {
+    /// Before the range: the line is highlighted, but not written out.
+    Hidden,
+    /// In the range, optionally preceded by a context marker.
+    Visible {
+        marker: bool,
+        label: Option<(Style, String)>,
+    },
+    /// After the range: the remaining lines can be skipped.
+    End { marker: bool },
+}
+
+impl LineRenderer {
+    /// `lines` is the number of lines, if it is known in advance.
+    pub(crate) fn new(
+        style: Option<&HighLightRes>,
+        theme: Option<&Theme>,
+        lines: Option<usize>,
+    ) -> Self {
+        let line_range = style.and_then(|s| s.get_line_range().clone());
+        let last_line = match (&line_range, lines) {
+            (Some(r), Some(n)) => Some(n.min(*r.get_lines().end())),
+            (_, n) => n,
+        };
+        let gutter = style
+            .and_then(|s| s.get_line_numbers().as_ref())
+            .map(|numbers| Gutter::new(numbers, last_line, theme));
+
+        Self {
+            sanitizer: Sanitizer::new(style),
+            gutter,
+            line_range,
+            marker_style: theme.map(gutter_style).unwrap_or_default(),
+            emphasis: style
+                .map(|s| s.get_emphasized_lines())
+                .filter(|lines| !lines.is_empty())
+                .zip(theme)
+                .map(|(lines, theme)| (lines.clone(), emphasis_color(theme))),
+            index: 0,
+            ended: false,
+        }
+    }
+
+    fn is_raw(&self) -> bool {
+        self.gutter.is_none()
+            && self.line_range.is_none()
+            && self.sanitizer.is_raw()
+    }
+
+    /// Moves on to the next line.
+    pub(crate) fn next_line(&mut self) -> NextLine {
+        let index = self.index;
+        self.index += 1;
+
+        let label = || self.gutter.as_ref().map(|g| g.label(index));
+        let Some(range) = &self.line_range else {
+            return NextLine::Visible {
+                marker: false,
+                label: label(),
+            };
+        };
+        let (lines, markers) = (range.get_lines(), *range.get_markers());
+        let number = index + 1;
+
+        if self.ended {
+            NextLine::End { marker: false }
+        } else if number > *lines.end() {
+            self.ended = true;
+            NextLine::End { marker: markers }
+        } else if number < *lines.start() {
+            NextLine::Hidden
+        } else {
+            NextLine::Visible {
+                marker: markers && number == *lines.start() && number > 1,
+                label: label(),
+            }
+        }
+    }
+
+    fn marker(&self) -> String {
+        match &self.gutter {
+            Some(g) => g.marker(),
+            _ => String::from("⋮\n"),
+        }
+    }
+
+    fn write_marker(
+        &self,
+        render: &mut dyn Render,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        render.line(&[(self.marker_style, &self.marker())], out)
+    }
+
+    /// Passes a complete line to the renderer.
+    ///
+    /// Returns false after the end of the line range, so that the remaining lines don't have to be highlighted.
+    pub(crate) fn line(
+        &mut self,
+        render: &mut dyn Render,
+        ranges: &[(Style, &str)],
+        out: &mut dyn Write,
+    ) -> Result<bool> {
+        match self.next_line() {
+            NextLine::Hidden => Ok(true),
+            NextLine::Visible { marker, label } => {
+                self.write(render, marker, label, ranges, out)?;
+                Ok(true)
+            }
+            NextLine::End { marker } => {
+                if marker {
+                    self.write_marker(render, out)?;
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    /// Passes (a part of) a visible line to the renderer, starting with `label`.
+    pub(crate) fn write(
+        &self,
+        render: &mut dyn Render,
+        marker: bool,
+        label: Option<(Style, String)>,
+        ranges: &[(Style, &str)],
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        if marker {
+            self.write_marker(render, out)?;
+        }
+        let background = self.emphasis.as_ref().and_then(|(lines, color)| {
+            // `index` has been moved on to the next line
+            lines.contains(&self.index).then_some(*color)
+        });
+        let render_line =
+            |render: &mut dyn Render, ranges: &[_], out| match background {
+                Some(bg) => render.emphasized_line(ranges, bg, out),
+                _ => render.line(ranges, out),
+            };
+
+        if label.is_none() && self.sanitizer.is_raw() {
+            return render_line(render, ranges, out);
+        }
+        let sanitized = self.sanitizer.ranges(ranges);
+        let ranges = label
+            .iter()
+            .map(|(style, s)| (*style, s.as_str()))
+            .chain(sanitized.iter().map(|(style, s)| (*style, s.as_ref())))
+            .collect::<Vec<_>>();
+        render_line(render, &ranges, out)
+    }
+
+    /// The text in front of the next line that is not highlighted: `Continue(None)` if the line is hidden, and `Break` with the last marker (if any) after the end of the line range.
+    fn plain_prefix(&mut self) -> ControlFlow<String, Option<String>> {
+        match self.next_line() {
+            NextLine::Hidden => ControlFlow::Continue(None),
+            NextLine::Visible { marker, label } => {
+                let mut prefix = String::new();
+                if marker {
+                    prefix.push_str(&self.marker());
+                }
+                if let Some((_, label)) = label {
+                    prefix.push_str(&label);
+                }
+                ControlFlow::Continue(Some(prefix))
+            }
+            NextLine::End { marker: true } => ControlFlow::Break(self.marker()),
+            NextLine::End { .. } => ControlFlow::Break(String::new()),
+        }
+    }
+
+    /// Like [`LineRenderer::plain_prefix`], but with the sanitized line.
+    fn plain_line<'a>(
+        &mut self,
+        line: &'a str,
+    ) -> ControlFlow<String, Cow<'a, str>> {
+        let prefix = match self.plain_prefix()? {
+            Some(prefix) => prefix,
+            _ => return ControlFlow::Continue(Cow::Borrowed("")),
+        };
+        let line = self.sanitizer.text(line);
+        match prefix.is_empty() {
+            true => ControlFlow::Continue(line),
+            _ => ControlFlow::Continue(Cow::Owned(prefix + &line)),
+        }
+    }
+
+    /// Like [`LineRenderer::plain_line`], but for all lines of `contents`.
+    pub(crate) fn plain<'a>(&mut self, contents: &'a str) -> Cow<'a, str> {
+        if self.gutter.is_none() && self.line_range.is_none() {
+            return self.sanitizer.text(contents);
+        }
+        let mut plain = String::with_capacity(contents.len());
+        for line in LinesWithEndings::from(contents) {
+            match self.plain_line(line) {
+                ControlFlow::Continue(s) => plain.push_str(&s),
+                ControlFlow::Break(s) => {
+                    plain.push_str(&s);
+                    break;
+                }
+            }
+        }
+        Cow::Owned(plain)
+    }
+}
+
+/// `lineHighlight` of the theme, falling back to `selection` and then to a faint foreground color.
+fn emphasis_color(theme: &Theme) -> Color {
+    let settings = &theme.settings;
+    settings
+        .line_highlight
+        .or(settings.selection)
+        .unwrap_or_else(|| {
+            let bg = settings.background.unwrap_or(Color::BLACK);
+            let fg = settings.foreground.unwrap_or(Color::WHITE);
+            blend_fg_color(Color { a: 0x30, ..fg }, bg)
+        })
+}
+
+/// Splits `bytes` into lines, including their line endings.
+fn split_lines(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    bytes.split_inclusive(|&b| b == b'\n')
+}
+
+/// Writes `contents` without highlighting, but with the control characters and invalid bytes replaced.
+fn write_plain_bytes(
+    contents: &[u8],
+    style: Option<&HighLightRes>,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let count = split_lines(contents).count();
+    let mut lines = LineRenderer::new(style, None, Some(count));
+
+    for line in split_lines(contents) {
+        match lines.plain_prefix() {
+            ControlFlow::Continue(Some(prefix)) => {
+                out.write_all(prefix.as_bytes())?
+            }
+            ControlFlow::Continue(None) => continue,
+            ControlFlow::Break(marker) => {
+                out.write_all(marker.as_bytes())?;
+                break;
+            }
+        }
+        for chunk in line.utf8_chunks() {
+            let valid = lines.sanitizer.text(chunk.valid());
+            out.write_all(valid.as_bytes())?;
+            out.write_all(&lines.sanitizer.invalid_bytes(chunk.invalid()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the lines of `reader` without highlighting, but with the control characters replaced.
+fn copy_plain_lines(
+    reader: &mut dyn BufRead,
+    mut lines: LineRenderer,
+    out: &mut dyn Write,
+) -> Result<()> {
+    if lines.is_raw() {
+        io::copy(reader, out)?;
+        return Ok(());
+    }
+    let mut line = String::new();
+    while reader.read_line(&mut line)? != 0 {
+        match lines.plain_line(&line) {
+            ControlFlow::Continue(s) => out.write_all(s.as_bytes())?,
+            ControlFlow::Break(s) => {
+                out.write_all(s.as_bytes())?;
+                break;
+            }
+        }
+        line.clear();
     }
-    out.write_all(b"\x1B[0m")?;
     Ok(())
 }
 
@@ -104,6 +960,7 @@ mod tests {
     use std::fs::File;
 
     use super::*;
+    use crate::{ControlChars, InvisibleChars, LineNumbers, LineRange};
 
     const S: &str = r#"
     [main]
@@ -113,21 +970,374 @@ mod tests {
     "#;
 
     #[test]
-    fn print_highlighted_text() -> io::Result<()> {
+    fn print_highlighted_text() -> Result<()> {
         let res = HighLightRes::default();
         gen_syntax_highlight("toml", S, Some(&res), None)
     }
 
     #[test]
     #[cfg(target_os = "linux")]
-    fn write_to_file() -> io::Result<()> {
+    fn write_to_file() -> Result<()> {
         let res = HighLightRes::default().with_background(false);
         let mut file = File::create("/tmp/test.txt")?;
         gen_syntax_highlight("toml", S, Some(&res), Some(&mut file))
     }
 
     #[test]
-    fn get_pwsh() -> io::Result<()> {
+    fn highlight_to_string() -> Result<()> {
+        let res = HighLightRes::default();
+        let s = gen_syntax_highlight_string("toml", S, Some(&res))?;
+
+        let mut v = Vec::new();
+        gen_syntax_highlight("toml", S, Some(&res), Some(&mut v))?;
+        assert_eq!(s.as_bytes(), v);
+
+        let s = gen_syntax_highlight_string("toml", S, None)?;
+        assert_eq!(s, S);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_syntax() {
+        use crate::syntax::{Fallback, SyntaxResolver};
+
+        let resolver = SyntaxResolver::default().with_fallback(Fallback::Error);
+        let res = HighLightRes::default().with_syntax_resolver(resolver);
+
+        // Sniffing would find TOML and RON, but the format is not empty.
+        for (fmt, s) in [("tomll", "a = 1\n"), ("py", "Point(1, 2)\n")] {
+            let err = gen_syntax_highlight_string(fmt, s, Some(&res));
+            assert!(matches!(err, Err(crate::Error::UnknownSyntax { .. })));
+        }
+        assert!(gen_syntax_highlight_string("", "a = 1\n", Some(&res)).is_ok());
+    }
+
+    #[test]
+    fn unknown_theme() {
+        let mut res = HighLightRes::default();
+        *res.get_name_mut() = "monokai".into();
+
+        let err = gen_syntax_highlight_string("toml", S, Some(&res));
+        assert!(matches!(err, Err(crate::Error::UnknownTheme { .. })));
+    }
+
+    #[test]
+    fn highlight_reader() -> Result<()> {
+        let res = HighLightRes::default();
+        let s = gen_syntax_highlight_string("toml", S, Some(&res))?;
+
+        let mut v = Vec::new();
+        let mut reader = io::BufReader::with_capacity(8, S.as_bytes());
+        gen_syntax_highlight_reader(
+            "toml",
+            &mut reader,
+            Some(&res),
+            Some(&mut v),
+        )?;
+        assert_eq!(s.as_bytes(), v);
+
+        let mut v = Vec::new();
+        gen_syntax_highlight_reader(
+            "toml",
+            &mut S.as_bytes(),
+            None,
+            Some(&mut v),
+        )?;
+        assert_eq!(S.as_bytes(), v);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_utf8() -> Result<()> {
+        let s = b"a = \"\xe9t\xe9\"\nb = 1\n";
+        let highlight = |policy, depth| -> Result<Vec<u8>> {
+            let res = HighLightRes::default()
+                .with_invalid_utf8(policy)
+                .with_color_depth(depth);
+            let mut v = Vec::new();
+            gen_syntax_highlight_bytes("toml", s, Some(&res), Some(&mut v))?;
+            Ok(v)
+        };
+
+        let v = highlight(InvalidUtf8::Passthrough, ColorDepth::NoColor)?;
+        assert_eq!(v, s);
+
+        let v = highlight(InvalidUtf8::Passthrough, ColorDepth::TrueColor)?;
+        assert!(v.windows(5).any(|x| x == b"\x1B[0m\xe9"));
+        assert!(v.ends_with(b"\x1B[0m"));
+
+        let v = highlight(InvalidUtf8::Lossy, ColorDepth::NoColor)?;
+        assert_eq!(v, "a = \"\u{FFFD}t\u{FFFD}\"\nb = 1\n".as_bytes());
+
+        let err = highlight(InvalidUtf8::Error, ColorDepth::NoColor);
+        assert!(
+            matches!(err, Err(crate::Error::Utf8(e)) if e.valid_up_to() == 5)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn control_chars() -> Result<()> {
+        let s = "title = \"\x1B]0;pwned\x07\"\r\n";
+        let highlight = |res: &HighLightRes| {
+            gen_syntax_highlight_string("toml", s, Some(res))
+        };
+
+        let res = HighLightRes::default();
+        let escaped = highlight(&res)?;
+        assert!(!escaped.contains("\x1B]"));
+        assert!(escaped.contains("^[]0;pwned^G"));
+        assert!(escaped.contains('\r') && !escaped.contains("^M"));
+
+        let res = res.with_control_chars(ControlChars::Raw);
+        assert!(highlight(&res)?.contains("\x1B]0;pwned\x07"));
+
+        assert_eq!(
+            gen_syntax_highlight_string("toml", s, None)?,
+            "title = \"^[]0;pwned^G\"\r\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invisible_chars() -> Result<()> {
+        let s = "admin = \"user\u{202E} \u{2066}// check if admin\u{2069}\"\n";
+        let res = HighLightRes::default().with_color_depth(ColorDepth::NoColor);
+
+        let kept = gen_syntax_highlight_string("toml", s, Some(&res))?;
+        assert_eq!(kept, s);
+
+        let res = res.with_invisible_chars(InvisibleChars::Reveal);
+        let revealed = gen_syntax_highlight_string("toml", s, Some(&res))?;
+        assert_eq!(
+            revealed,
+            "admin = \"user<U+202E> <U+2066>// check if admin<U+2069>\"\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn line_numbers() -> Result<()> {
+        let s = "a = 1\nb = 2\n";
+        let numbers = LineNumbers::default().with_start(9);
+        let res = HighLightRes::default()
+            .with_color_depth(ColorDepth::NoColor)
+            .with_line_numbers(Some(numbers));
+
+        let expected = " 9 │ a = 1\n10 │ b = 2\n";
+        assert_eq!(
+            gen_syntax_highlight_string("toml", s, Some(&res))?,
+            expected
+        );
+
+        let mut v = Vec::new();
+        gen_syntax_highlight_reader(
+            "toml",
+            &mut s.as_bytes(),
+            Some(&res),
+            Some(&mut v),
+        )?;
+        assert_eq!(v, "   9 │ a = 1\n  10 │ b = 2\n".as_bytes());
+
+        let mut res = res;
+        *res.get_name_mut() = "None".into();
+        assert_eq!(
+            gen_syntax_highlight_string("toml", s, Some(&res))?,
+            expected
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn line_range() -> Result<()> {
+        // the multi-line string starts above the range
+        let s = "a = \"\"\"\nb = 1\n\"\"\"\nc = 2\nd = 3\n";
+        let full = HighLightRes::default()
+            .with_line_numbers(Some(LineNumbers::default()));
+        let res = full
+            .clone()
+            .with_line_range(Some(LineRange::new(2..=4)))
+            .with_color_depth(ColorDepth::NoColor);
+
+        let highlighted = gen_syntax_highlight_string("toml", s, Some(&res))?;
+        assert_eq!(highlighted, "2 │ b = 1\n3 │ \"\"\"\n4 │ c = 2\n");
+
+        // line 3 closes the string in both outputs
+        let full = gen_syntax_highlight_string("toml", s, Some(&full))?;
+        let res = res.with_color_depth(ColorDepth::TrueColor);
+        let part = gen_syntax_highlight_string("toml", s, Some(&res))?;
+        let nth_line =
+            |s: &str, n| s.split_inclusive('\n').nth(n).map(str::to_owned);
+        assert_eq!(nth_line(&part, 1), nth_line(&full, 2));
+
+        let range = LineRange::new(2..=4).with_markers(true);
+        let res = res
+            .with_line_range(Some(range))
+            .with_color_depth(ColorDepth::NoColor)
+            .with_line_numbers(None);
+        let mut plain = res.clone();
+        *plain.get_name_mut() = "None".into();
+
+        let expected = "⋮\nb = 1\n\"\"\"\nc = 2\n⋮\n";
+        assert_eq!(
+            gen_syntax_highlight_string("toml", s, Some(&res))?,
+            expected
+        );
+        assert_eq!(
+            gen_syntax_highlight_string("toml", s, Some(&plain))?,
+            expected
+        );
+
+        let mut v = Vec::new();
+        gen_syntax_highlight_reader(
+            "toml",
+            &mut s.as_bytes(),
+            Some(&res),
+            Some(&mut v),
+        )?;
+        assert_eq!(v, expected.as_bytes());
+        Ok(())
+    }
+
+    #[test]
+    fn emphasized_lines() -> Result<()> {
+        let s = "a = 1\nb = 2\n";
+        let res = HighLightRes::default()
+            .with_background(false)
+            .with_emphasized_lines([2]);
+        let highlighted = gen_syntax_highlight_string("toml", s, Some(&res))?;
+        let (first, second) = highlighted.split_once('\n').unwrap_or_default();
+
+        // lineHighlight of the default theme
+        let bg = "\x1B[48;2;51;51;51m";
+        assert!(!first.contains(bg));
+        assert!(second.contains(bg));
+        assert!(second.ends_with("\x1B[K\x1B[49m\n\x1B[0m"));
+
+        let res = res.with_color_depth(ColorDepth::NoColor);
+        assert_eq!(gen_syntax_highlight_string("toml", s, Some(&res))?, s);
+        Ok(())
+    }
+
+    #[test]
+    fn overlays() -> Result<()> {
+        use crate::{FontStyle, StyleModifier};
+
+        let s = "a = 1\nb = 2\n";
+        let inverse = StyleModifier {
+            foreground: Some(Color::BLACK),
+            background: Some(Color::WHITE),
+            font_style: Some(FontStyle::UNDERLINE),
+        };
+        // "1\nb"
+        let res = HighLightRes::default()
+            .with_background(false)
+            .with_overlays([(4..7, inverse)]);
+        let highlighted = gen_syntax_highlight_string("toml", s, Some(&res))?;
+
+        let (bg, fg) = ("\x1B[48;2;255;255;255m", "\x1B[38;2;0;0;0m");
+        assert_eq!(highlighted.matches(bg).count(), 3);
+        assert!(highlighted.contains(&format!("{bg}{fg}\x1B[4m1")));
+        assert!(highlighted.contains(&format!("{bg}{fg}b\x1B[49m")));
+        assert!(highlighted.contains("\x1B[24m "));
+
+        let mut streamed = Vec::new();
+        gen_syntax_highlight_reader(
+            "toml",
+            &mut s.as_bytes(),
+            Some(&res),
+            Some(&mut streamed),
+        )?;
+        assert_eq!(String::from_utf8_lossy(&streamed), highlighted);
+        Ok(())
+    }
+
+    #[test]
+    fn scope_backgrounds() -> Result<()> {
+        use crate::{resource::ThemeSet, StyleModifier};
+        use std::str::FromStr;
+        use syntect::highlighting::{ScopeSelectors, ThemeItem};
+
+        let mut theme = HighLightRes::default().try_theme_once()?.clone();
+        let white = StyleModifier {
+            background: Some(Color::WHITE),
+            ..Default::default()
+        };
+        theme.scopes.push(ThemeItem {
+            scope: ScopeSelectors::from_str("source").expect("valid selector"),
+            style: white,
+        });
+        let mut set = ThemeSet::new();
+        set.themes.insert("scoped".into(), theme);
+
+        let s = "a = 1\nb = 2\n";
+        let bg = "\x1B[48;2;255;255;255m";
+        let res = HighLightRes::new("scoped".into(), &set)
+            .with_background(false)
+            .with_color_depth(ColorDepth::TrueColor);
+        let highlighted = gen_syntax_highlight_string("toml", s, Some(&res))?;
+        assert!(!highlighted.contains("\x1B[48;"));
+
+        let res = res.with_overlays([(4..5, white)]);
+        let highlighted = gen_syntax_highlight_string("toml", s, Some(&res))?;
+        assert_eq!(highlighted.matches(bg).count(), 1);
+        assert!(highlighted.contains("1\x1B[49m"));
+        Ok(())
+    }
+
+    #[test]
+    fn display() -> Result<()> {
+        let res = HighLightRes::default().with_background(false);
+        let s = gen_syntax_highlight_string("toml", S, Some(&res))?;
+
+        assert_eq!(Highlighted::new("toml", S, &res).to_string(), s);
+        assert_eq!(format!("{}", res.highlight("toml", S)), s);
+        Ok(())
+    }
+
+    #[test]
+    fn color_depth() -> Result<()> {
+        let highlight = |depth| -> Result<String> {
+            let res = HighLightRes::default().with_color_depth(depth);
+            let mut v = Vec::new();
+            gen_syntax_highlight("toml", S, Some(&res), Some(&mut v))?;
+            Ok(String::from_utf8(v).expect("Invalid UTF-8"))
+        };
+
+        assert!(highlight(ColorDepth::TrueColor)?.contains("\x1B[38;2;"));
+
+        let s = highlight(ColorDepth::Ansi256)?;
+        assert!(s.contains("\x1B[38;5;") && !s.contains(";2;"));
+
+        let s = highlight(ColorDepth::Ansi16)?;
+        assert!(!s.contains("\x1B[38;"));
+
+        assert_eq!(highlight(ColorDepth::NoColor)?, S);
+        Ok(())
+    }
+
+    #[test]
+    fn font_style() -> Result<()> {
+        // Monokai Extended: `markup.italic` and `markup.bold`
+        let s = "*italic* **bold**\n";
+        let highlight = |res: &HighLightRes| -> Result<String> {
+            let mut v = Vec::new();
+            gen_syntax_highlight("md", s, Some(res), Some(&mut v))?;
+            Ok(String::from_utf8(v).expect("Invalid UTF-8"))
+        };
+
+        let res = HighLightRes::default();
+        let out = highlight(&res)?;
+        assert!(out.contains("\x1B[3m") && out.contains("\x1B[23m"));
+        assert!(out.contains("\x1B[1m"));
+
+        let out = highlight(&res.with_font_style(false))?;
+        assert!(!out.contains("\x1B[3m") && !out.contains("\x1B[1m"));
+        Ok(())
+    }
+
+    #[test]
+    fn get_pwsh() -> Result<()> {
         let s = r#"
         using namespace System
 
@@ -154,7 +1364,7 @@ mod tests {
     }
 
     #[test]
-    fn zsh_high_light() -> io::Result<()> {
+    fn zsh_high_light() -> Result<()> {
         let s = r#"
         #compdef tomlyre
 
@@ -0,0 +1,100 @@
+use std::ops::Range;
+pub use syntect::highlighting::{FontStyle, StyleModifier};
+use syntect::{
+    highlighting::{Color, Style},
+    util::modify_range,
+};
+
+/// Applies the overlays of [`HighLightRes`](crate::HighLightRes) to highlighted lines, in the order they come.
+///
+/// The ranges are byte offsets into the whole text, so the offset of the current line is tracked.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Overlays<'a> {
+    overlays: &'a [(Range<usize>, StyleModifier)],
+    /// The byte offset of the next line.
+    offset: usize,
+    /// If set, every span gets this background before the overlays are applied, so only the overlays can change it.
+    background: Option<Color>,
+}
+
+impl<'a> Overlays<'a> {
+    pub(crate) fn new(overlays: &'a [(Range<usize>, StyleModifier)]) -> Self {
+        Self {
+            overlays,
+            offset: 0,
+            background: None,
+        }
+    }
+
+    /// Resets the background of every span to `background` (e.g. the background of the theme) before the overlays are applied.
+    pub(crate) fn with_background(self, background: Option<Color>) -> Self {
+        Self { background, ..self }
+    }
+
+    /// Modifies the styles of the spans of the next line that fall into any overlay. Later overlays are applied on top of earlier ones.
+    ///
+    /// An offset inside of a multi-byte character is moved back to the start of the character.
+    pub(crate) fn apply<'s>(
+        &mut self,
+        mut ranges: Vec<(Style, &'s str)>,
+    ) -> Vec<(Style, &'s str)> {
+        let start = self.offset;
+        let len = ranges.iter().map(|(_, s)| s.len()).sum::<usize>();
+        self.offset += len;
+
+        if let Some(background) = self.background {
+            for (style, _) in &mut ranges {
+                style.background = background;
+            }
+        }
+        for (range, modifier) in self.overlays {
+            let from = range.start.max(start);
+            let to = range.end.min(start + len);
+            if from < to {
+                ranges =
+                    modify_range(&ranges, from - start..to - start, *modifier);
+            }
+        }
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn spans_across_lines() {
+        let plain = Style::default();
+        let underline = StyleModifier {
+            font_style: Some(FontStyle::UNDERLINE),
+            ..Default::default()
+        };
+        let red = StyleModifier {
+            foreground: Some(Color {
+                r: 255,
+                g: 0,
+                b: 0,
+                a: 255,
+            }),
+            ..Default::default()
+        };
+        let overlays = [(2..8, underline), (7..8, red)];
+        let mut overlays = Overlays::new(&overlays);
+
+        let first = overlays.apply(vec![(plain, "ab"), (plain, "cd\n")]);
+        assert_eq!(first, [(plain, "ab"), (plain.apply(underline), "cd\n"),]);
+
+        let second = overlays.apply(vec![(plain, "efgh\n")]);
+        assert_eq!(
+            second,
+            [
+                (plain.apply(underline), "ef"),
+                (plain.apply(underline).apply(red), "g"),
+                (plain, "h\n"),
+            ]
+        );
+        assert_eq!(overlays.apply(vec![(plain, "ij")]), [(plain, "ij")]);
+    }
+}
@@ -0,0 +1,46 @@
+use getset::Getters;
+use std::ops::RangeInclusive;
+
+/// Only the lines in this range (1-based, inclusive) are written out.
+///
+/// The lines before the range are still parsed, so that e.g. a multi-line string that starts above the range is highlighted correctly.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{gen_syntax_highlight_string, HighLightRes, LineRange};
+///
+/// let s = "a = 1\nb = 2\nc = 3\nd = 4\n";
+/// let range = LineRange::new(2..=3).with_markers(true);
+/// let res = HighLightRes::default().with_line_range(Some(range));
+///
+/// let highlighted = gen_syntax_highlight_string("toml", s, Some(&res))
+///     .expect("Failed to get highlighted text");
+///
+/// assert!(!highlighted.contains("a = 1"));
+/// assert!(!highlighted.contains("d = 4"));
+/// assert_eq!(highlighted.matches('⋮').count(), 2);
+/// ```
+#[derive(Getters, Debug, Clone, PartialEq, Eq)]
+#[getset(get = "pub with_prefix")]
+pub struct LineRange {
+    lines: RangeInclusive<usize>,
+    markers: bool,
+}
+
+impl LineRange {
+    pub fn new(lines: RangeInclusive<usize>) -> Self {
+        Self {
+            lines,
+            markers: false,
+        }
+    }
+
+    /// Enable or disable a `⋮` line above and below the range if lines are omitted there.
+    pub fn with_markers(self, switch: bool) -> Self {
+        Self {
+            markers: switch,
+            ..self
+        }
+    }
+}
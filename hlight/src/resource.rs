@@ -1,10 +1,20 @@
 use getset::{Getters, MutGetters};
 use once_cell::sync::OnceCell;
-use std::borrow::Cow;
-pub use syntect::{dumps, highlighting::ThemeSet};
+use std::{borrow::Cow, collections::BTreeSet, ops::Range};
+pub use syntect::highlighting::ThemeSet;
 use syntect::{highlighting::Theme, parsing::SyntaxSet};
 
-use crate::theme::theme_monokai;
+use crate::{
+    color::ColorDepth,
+    error::Error,
+    gutter::LineNumbers,
+    output::InvalidUtf8,
+    overlay::StyleModifier,
+    range::LineRange,
+    sanitize::{ControlChars, InvisibleChars},
+    syntax::SyntaxResolver,
+    theme::theme_monokai,
+};
 
 #[derive(Getters, MutGetters, Debug, Clone)]
 #[getset(get = "pub with_prefix", get_mut = "pub with_prefix")]
@@ -14,6 +24,16 @@ pub struct HighLightRes<'name> {
     theme_set: &'name ThemeSet,
     syntax_set: &'static SyntaxSet,
     background: bool,
+    color_depth: ColorDepth,
+    font_style: bool,
+    invalid_utf8: InvalidUtf8,
+    control_chars: ControlChars,
+    invisible_chars: InvisibleChars,
+    line_numbers: Option<LineNumbers>,
+    line_range: Option<LineRange>,
+    emphasized_lines: BTreeSet<usize>,
+    overlays: Vec<(Range<usize>, StyleModifier)>,
+    syntax_resolver: SyntaxResolver,
 }
 
 impl<'name> HighLightRes<'name> {
@@ -43,8 +63,36 @@ impl<'name> HighLightRes<'name> {
         }
     }
 
+    /// Like [`HighLightRes::new`], but returns [`Error::UnknownTheme`] if the theme name does not exist in `theme_set`.
+    ///
+    /// "None" is accepted, which means no highlighting.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::HighLightRes;
+    /// use std::borrow::Cow;
+    ///
+    /// let set = HighLightRes::static_theme_set();
+    ///
+    /// assert!(HighLightRes::try_new(Cow::from("ayu-dark"), set).is_ok());
+    /// assert!(HighLightRes::try_new(Cow::from("ayu-drak"), set).is_err());
+    /// ```
+    pub fn try_new(
+        name: Cow<'name, str>,
+        theme_set: &'name ThemeSet,
+    ) -> Result<Self, Error> {
+        let res = Self::new(name, theme_set);
+        if res.get_name() != "None" {
+            res.try_theme_once()?;
+        }
+        Ok(res)
+    }
+
     /// Enable or disable background
     ///
+    /// If disabled, the scope backgrounds of the theme are dropped too, and only the overlays (see [`HighLightRes::with_overlays`]) and emphasized lines add a background.
+    ///
     /// # Example
     ///
     /// ```
@@ -59,6 +107,207 @@ impl<'name> HighLightRes<'name> {
             ..self
         }
     }
+
+    /// Sets the color depth of the terminal output.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::{color::ColorDepth, HighLightRes};
+    ///
+    /// let res = HighLightRes::default().with_color_depth(ColorDepth::Ansi256);
+    /// assert_eq!(*res.get_color_depth(), ColorDepth::Ansi256)
+    /// ```
+    pub fn with_color_depth(self, depth: ColorDepth) -> Self {
+        Self {
+            color_depth: depth,
+            ..self
+        }
+    }
+
+    /// Enable or disable bold, italic and underline in the terminal output.
+    ///
+    /// Some terminals render italics badly, so you may want to turn it off.
+    ///
+    /// Note: `FontStyle` of syntect has no strikethrough, so `strikethrough` in tmTheme files is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::HighLightRes;
+    ///
+    /// let res = HighLightRes::default().with_font_style(false);
+    /// assert!(!res.get_font_style())
+    /// ```
+    pub fn with_font_style(self, switch: bool) -> Self {
+        Self {
+            font_style: switch,
+            ..self
+        }
+    }
+
+    /// Sets how invalid UTF-8 is handled by [`gen_syntax_highlight_bytes`](crate::gen_syntax_highlight_bytes).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::{HighLightRes, InvalidUtf8};
+    ///
+    /// let res = HighLightRes::default().with_invalid_utf8(InvalidUtf8::Passthrough);
+    /// assert_eq!(*res.get_invalid_utf8(), InvalidUtf8::Passthrough)
+    /// ```
+    pub fn with_invalid_utf8(self, policy: InvalidUtf8) -> Self {
+        Self {
+            invalid_utf8: policy,
+            ..self
+        }
+    }
+
+    /// Sets how control characters (e.g. ESC) in the contents are written out.
+    ///
+    /// By default, they are replaced with visible placeholders, so that untrusted files cannot inject terminal escape sequences.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::{ControlChars, HighLightRes};
+    ///
+    /// let res = HighLightRes::default().with_control_chars(ControlChars::Hex);
+    /// assert_eq!(*res.get_control_chars(), ControlChars::Hex)
+    /// ```
+    pub fn with_control_chars(self, mode: ControlChars) -> Self {
+        Self {
+            control_chars: mode,
+            ..self
+        }
+    }
+
+    /// Sets how bidi controls, zero-width characters and other invisible characters are written out.
+    ///
+    /// It is useful for reviewing untrusted files, where these characters can make the code look different from what it is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::{gen_syntax_highlight_string, HighLightRes, InvisibleChars};
+    ///
+    /// let res = HighLightRes::default().with_invisible_chars(InvisibleChars::Reveal);
+    /// let s = gen_syntax_highlight_string("toml", "a = \"\u{202E}\"\n", Some(&res))
+    ///     .expect("Failed to get highlighted text");
+    ///
+    /// assert!(s.contains("<U+202E>"));
+    /// ```
+    pub fn with_invisible_chars(self, mode: InvisibleChars) -> Self {
+        Self {
+            invisible_chars: mode,
+            ..self
+        }
+    }
+
+    /// Enable or disable line numbers. See [`LineNumbers`].
+    ///
+    /// They are supported by the terminal output, [`gen_html_highlight`](crate::gen_html_highlight) and [`gen_svg_highlight`](crate::gen_svg_highlight).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::{HighLightRes, LineNumbers};
+    ///
+    /// let res = HighLightRes::default()
+    ///     .with_line_numbers(Some(LineNumbers::default().with_start(120)));
+    /// assert_eq!(res.get_line_numbers().as_ref().map(|x| *x.get_start()), Some(120))
+    /// ```
+    pub fn with_line_numbers(self, line_numbers: Option<LineNumbers>) -> Self {
+        Self {
+            line_numbers,
+            ..self
+        }
+    }
+
+    /// Only writes out the lines in a range. See [`LineRange`].
+    ///
+    /// It is not supported by [`gen_classed_html_highlight`](crate::html::gen_classed_html_highlight).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::{HighLightRes, LineRange};
+    ///
+    /// let res = HighLightRes::default()
+    ///     .with_line_range(Some(LineRange::new(120..=140).with_markers(true)));
+    /// assert!(res.get_line_range().is_some())
+    /// ```
+    pub fn with_line_range(self, line_range: Option<LineRange>) -> Self {
+        Self { line_range, ..self }
+    }
+
+    /// Emphasizes lines (1-based) with a full-width background in the `lineHighlight` color of the theme, e.g. the line that an error points to.
+    ///
+    /// It is supported by the terminal output, [`gen_html_highlight`](crate::gen_html_highlight) and [`gen_svg_highlight`](crate::gen_svg_highlight), but not by [`gen_classed_html_highlight`](crate::html::gen_classed_html_highlight).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::HighLightRes;
+    ///
+    /// let res = HighLightRes::default().with_emphasized_lines([3, 5]);
+    /// assert!(res.get_emphasized_lines().contains(&3))
+    /// ```
+    pub fn with_emphasized_lines<I>(self, lines: I) -> Self
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        Self {
+            emphasized_lines: lines.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Overlays extra styles on byte ranges of the contents, on top of the syntax highlighting, e.g. for search matches or diagnostics.
+    ///
+    /// Each [`StyleModifier`] only replaces the fields that are set, so the syntax colors stay underneath. Overlays may span multiple lines, and later overlays are applied on top of earlier ones. With [`InvalidUtf8::Lossy`](crate::InvalidUtf8::Lossy) or [`InvalidUtf8::Passthrough`](crate::InvalidUtf8::Passthrough), the offsets refer to the decoded text. Nothing is overlaid if the theme name is "None".
+    ///
+    /// It is not supported by [`gen_classed_html_highlight`](crate::html::gen_classed_html_highlight).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::{FontStyle, HighLightRes, StyleModifier};
+    ///
+    /// let underline = StyleModifier {
+    ///     font_style: Some(FontStyle::UNDERLINE),
+    ///     ..Default::default()
+    /// };
+    /// // "enabled" in "[main]\nenabled = false\n"
+    /// let res = HighLightRes::default().with_overlays([(7..14, underline)]);
+    /// assert_eq!(res.get_overlays().len(), 1)
+    /// ```
+    pub fn with_overlays<I>(self, overlays: I) -> Self
+    where
+        I: IntoIterator<Item = (Range<usize>, StyleModifier)>,
+    {
+        Self {
+            overlays: overlays.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Sets how format strings (e.g. `"md"`) are resolved to syntaxes, e.g. to register aliases of an application or syntaxes of a custom syntax set. See [`SyntaxResolver`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::{syntax::SyntaxResolver, HighLightRes};
+    ///
+    /// let resolver = SyntaxResolver::default().with_alias("conf", ["INI"]);
+    /// let res = HighLightRes::default().with_syntax_resolver(resolver);
+    /// ```
+    pub fn with_syntax_resolver(self, resolver: SyntaxResolver) -> Self {
+        Self {
+            syntax_resolver: resolver,
+            ..self
+        }
+    }
 }
 
 impl<'name> Default for HighLightRes<'name> {
@@ -69,6 +318,16 @@ impl<'name> Default for HighLightRes<'name> {
             syntax_set: Self::static_syntax_set(),
             theme_set: Self::static_theme_set(),
             background: true,
+            color_depth: ColorDepth::default(),
+            font_style: true,
+            invalid_utf8: InvalidUtf8::default(),
+            control_chars: ControlChars::default(),
+            invisible_chars: InvisibleChars::default(),
+            line_numbers: None,
+            line_range: None,
+            emphasized_lines: BTreeSet::new(),
+            overlays: Vec::new(),
+            syntax_resolver: SyntaxResolver::default(),
         }
     }
 }
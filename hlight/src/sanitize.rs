@@ -0,0 +1,333 @@
+use crate::resource::HighLightRes;
+use std::{borrow::Cow, ops::Range};
+use syntect::highlighting::{FontStyle, Style};
+
+/// How control characters in the contents are written out.
+///
+/// Terminals interpret control characters, so a malicious file could e.g. change the window title, create hyperlinks or move the cursor. By default, they are replaced with visible placeholders.
+///
+/// Tabs and line endings (`\n`, `\r\n`) are always kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControlChars {
+    /// The caret notation of `cat -v`, e.g. `^[` for ESC, `^?` for DEL and `M-^[` for U+009B (CSI).
+    #[default]
+    Caret,
+    /// Hexadecimal escapes, e.g. `\x1b` for ESC and `\x9b` for U+009B (CSI).
+    Hex,
+    /// Writes control characters unchanged. Only use it for trusted contents.
+    Raw,
+}
+
+/// How invisible characters that can hide or reorder text are written out.
+///
+/// They include the Unicode bidi controls used in "Trojan Source" attacks (e.g. U+202E RIGHT-TO-LEFT OVERRIDE), zero-width characters (e.g. U+200B ZERO WIDTH SPACE) and other code points that render as nothing (e.g. U+3164 HANGUL FILLER, tag characters).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvisibleChars {
+    /// Writes them unchanged.
+    #[default]
+    Keep,
+    /// Replaces them with a marker like `<U+202E>`, which is rendered bold and underlined.
+    ///
+    /// Note: zero-width joiners are also revealed, so emoji sequences like 👨‍👩‍👧 are split up.
+    Reveal,
+}
+
+impl ControlChars {
+    /// Appends the placeholder of a control character (C0, DEL or C1) or a raw byte in 0x80..=0x9F.
+    fn push_placeholder(self, s: &mut String, code: u32) {
+        match (self, code) {
+            (Self::Caret, 0x7F) => s.push_str("^?"),
+            (Self::Caret, 0x80..) => {
+                s.push_str("M-");
+                self.push_placeholder(s, code - 0x80)
+            }
+            (Self::Caret, _) => {
+                s.push('^');
+                s.extend(char::from_u32(code + 0x40));
+            }
+            _ => s.push_str(&format!("\\x{code:02x}")),
+        }
+    }
+}
+
+/// Whether `c` has to be replaced. `next` is the character after `c`, so that a lone `\r` (which moves the cursor back) is replaced, but `\r\n` is not.
+fn is_unsafe(c: char, next: Option<char>) -> bool {
+    match c {
+        '\t' | '\n' => false,
+        '\r' => next != Some('\n'),
+        _ => c.is_control(),
+    }
+}
+
+/// Replaces control characters in `s` according to `mode`.
+pub(crate) fn escape_control_chars(
+    s: &str,
+    mode: ControlChars,
+) -> Cow<'_, str> {
+    escape_followed_by(s, mode, None)
+}
+
+/// Like [`escape_control_chars`], but `s` is followed by `after`, e.g. the first character of the next span.
+fn escape_followed_by(
+    s: &str,
+    mode: ControlChars,
+    after: Option<char>,
+) -> Cow<'_, str> {
+    let unsafe_chars = || {
+        let mut chars = s.chars().peekable();
+        std::iter::from_fn(move || {
+            let c = chars.next()?;
+            let next = chars.peek().copied().or(after);
+            Some((c, is_unsafe(c, next)))
+        })
+    };
+
+    if mode == ControlChars::Raw || !unsafe_chars().any(|(_, x)| x) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut escaped = String::with_capacity(s.len() + 8);
+    for (c, is_unsafe) in unsafe_chars() {
+        match is_unsafe {
+            true => mode.push_placeholder(&mut escaped, c as u32),
+            _ => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Whether `c` is a bidi control, a zero-width character or another invisible code point.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        // bidi controls
+        '\u{061C}'
+            | '\u{200E}'
+            | '\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2066}'..='\u{2069}'
+            // zero-width characters
+            | '\u{200B}'..='\u{200D}'
+            | '\u{2060}'
+            | '\u{FEFF}'
+            | '\u{180E}'
+            // other invisible code points
+            | '\u{00AD}'
+            | '\u{034F}'
+            | '\u{115F}'
+            | '\u{1160}'
+            | '\u{17B4}'
+            | '\u{17B5}'
+            | '\u{2061}'..='\u{2064}'
+            | '\u{3164}'
+            | '\u{FFA0}'
+            | '\u{E0000}'..='\u{E007F}'
+    )
+}
+
+fn marker(c: char) -> String {
+    format!("<U+{:04X}>", c as u32)
+}
+
+/// Splits the spans that contain invisible characters, and replaces these characters with markers.
+fn reveal_ranges<'a>(
+    ranges: Vec<(Style, Cow<'a, str>)>,
+) -> Vec<(Style, Cow<'a, str>)> {
+    let mut revealed = Vec::with_capacity(ranges.len());
+
+    for (style, text) in ranges {
+        if !text.chars().any(is_invisible) {
+            revealed.push((style, text));
+            continue;
+        }
+        let sub = |r: Range<usize>| match &text {
+            Cow::Borrowed(s) => Cow::Borrowed(&s[r]),
+            Cow::Owned(s) => Cow::Owned(s[r].to_owned()),
+        };
+        let marker_style = Style {
+            font_style: style.font_style
+                | FontStyle::BOLD
+                | FontStyle::UNDERLINE,
+            ..style
+        };
+        let mut start = 0;
+
+        for (i, c) in text.char_indices().filter(|(_, c)| is_invisible(*c)) {
+            if start < i {
+                revealed.push((style, sub(start..i)));
+            }
+            revealed.push((marker_style, Cow::Owned(marker(c))));
+            start = i + c.len_utf8();
+        }
+        if start < text.len() {
+            revealed.push((style, sub(start..text.len())));
+        }
+    }
+    revealed
+}
+
+/// Replaces invisible characters in `s` with markers.
+fn reveal(s: Cow<'_, str>) -> Cow<'_, str> {
+    if !s.chars().any(is_invisible) {
+        return s;
+    }
+    let mut revealed = String::with_capacity(s.len() + 16);
+    for c in s.chars() {
+        match is_invisible(c) {
+            true => revealed.push_str(&marker(c)),
+            _ => revealed.push(c),
+        }
+    }
+    Cow::Owned(revealed)
+}
+
+/// Replaces raw bytes that C1 control characters are made of in 8-bit terminals (0x80..=0x9F).
+///
+/// It is only used for invalid UTF-8, so the other bytes are kept as is.
+pub(crate) fn escape_invalid_bytes(
+    bytes: &[u8],
+    mode: ControlChars,
+) -> Cow<'_, [u8]> {
+    if mode == ControlChars::Raw
+        || !bytes.iter().any(|b| (0x80..=0x9F).contains(b))
+    {
+        return Cow::Borrowed(bytes);
+    }
+
+    let mut escaped = Vec::with_capacity(bytes.len() * 4);
+    for &b in bytes {
+        match b {
+            0x80..=0x9F => {
+                let mut s = String::new();
+                mode.push_placeholder(&mut s, b as u32);
+                escaped.extend_from_slice(s.as_bytes());
+            }
+            _ => escaped.push(b),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Applies [`escape_control_chars`] to every span of a highlighted line.
+///
+/// syntect may split `\r\n` into two spans, so every span is checked together with the first character of the next one.
+pub(crate) fn escape_ranges<'a>(
+    ranges: &[(Style, &'a str)],
+    mode: ControlChars,
+) -> Vec<(Style, Cow<'a, str>)> {
+    ranges
+        .iter()
+        .enumerate()
+        .map(|(i, &(style, text))| {
+            let after =
+                ranges.get(i + 1).and_then(|(_, next)| next.chars().next());
+            (style, escape_followed_by(text, mode, after))
+        })
+        .collect()
+}
+
+/// Makes the contents safe and readable before rendering, according to [`ControlChars`] and [`InvisibleChars`].
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Sanitizer {
+    control_chars: ControlChars,
+    invisible_chars: InvisibleChars,
+}
+
+impl Sanitizer {
+    /// The options of `style`, or the defaults if there is no style.
+    pub(crate) fn new(style: Option<&HighLightRes>) -> Self {
+        style
+            .map(|s| Self {
+                control_chars: *s.get_control_chars(),
+                invisible_chars: *s.get_invisible_chars(),
+            })
+            .unwrap_or_default()
+    }
+
+    /// Whether the contents are written unchanged.
+    pub(crate) fn is_raw(self) -> bool {
+        self.control_chars == ControlChars::Raw
+            && self.invisible_chars == InvisibleChars::Keep
+    }
+
+    pub(crate) fn text(self, s: &str) -> Cow<'_, str> {
+        let s = escape_control_chars(s, self.control_chars);
+        match self.invisible_chars {
+            InvisibleChars::Reveal => reveal(s),
+            _ => s,
+        }
+    }
+
+    pub(crate) fn ranges<'a>(
+        self,
+        ranges: &[(Style, &'a str)],
+    ) -> Vec<(Style, Cow<'a, str>)> {
+        let ranges = escape_ranges(ranges, self.control_chars);
+        match self.invisible_chars {
+            InvisibleChars::Reveal => reveal_ranges(ranges),
+            _ => ranges,
+        }
+    }
+
+    pub(crate) fn invalid_bytes(self, bytes: &[u8]) -> Cow<'_, [u8]> {
+        escape_invalid_bytes(bytes, self.control_chars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_chars() {
+        let s = "a\t\x1B]0;title\x07\r\n\u{9B}31m\x7F\rb\n";
+
+        assert_eq!(
+            escape_control_chars(s, ControlChars::Caret),
+            "a\t^[]0;title^G\r\nM-^[31m^?^Mb\n"
+        );
+        assert_eq!(
+            escape_control_chars(s, ControlChars::Hex),
+            "a\t\\x1b]0;title\\x07\r\n\\x9b31m\\x7f\\x0db\n"
+        );
+        assert_eq!(escape_control_chars(s, ControlChars::Raw), s);
+        assert!(matches!(
+            escape_control_chars("a\tb\r\n", ControlChars::Caret),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn split_crlf() {
+        let style = Style::default();
+        let ranges =
+            [(style, "a\r"), (style, "\n"), (style, "\r"), (style, "b")];
+        let escaped = escape_ranges(&ranges, ControlChars::Caret);
+
+        let escaped =
+            escaped.iter().map(|(_, s)| s.as_ref()).collect::<String>();
+        assert_eq!(escaped, "a\r\n^Mb");
+    }
+
+    #[test]
+    fn invisible_chars() {
+        let style = Style::default();
+        let ranges = [(style, "a\u{200B}b"), (style, "\u{202E}")];
+        let revealed = reveal_ranges(escape_ranges(&ranges, ControlChars::Raw));
+
+        let text = revealed.iter().map(|(_, s)| s.as_ref()).collect::<Vec<_>>();
+        assert_eq!(text, ["a", "<U+200B>", "b", "<U+202E>"]);
+        assert!(revealed[1].0.font_style.contains(FontStyle::UNDERLINE));
+        assert_eq!(revealed[2].0, style);
+
+        assert_eq!(reveal("x\u{E0041}".into()), "x<U+E0041>");
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        let b = b"\x9B\xE9";
+        assert_eq!(*escape_invalid_bytes(b, ControlChars::Caret), *b"M-^[\xE9");
+        assert_eq!(*escape_invalid_bytes(b, ControlChars::Hex), *b"\\x9b\xE9");
+        assert_eq!(*escape_invalid_bytes(b, ControlChars::Raw), *b);
+    }
+}
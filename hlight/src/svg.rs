@@ -0,0 +1,291 @@
+use crate::{
+    color::{blend_fg_color, hex_color},
+    error::Result,
+    html::escape_html,
+    output::{render_highlight, Render},
+    resource::HighLightRes,
+};
+use std::io::Write;
+use syntect::{
+    highlighting::{Color, FontStyle, Style, Theme},
+    util::LinesWithEndings,
+};
+
+const FONT_SIZE: f32 = 14.0;
+const LINE_HEIGHT: f32 = 20.0;
+/// Monospace fonts are roughly 0.6em wide.
+const CHAR_WIDTH: f32 = FONT_SIZE * 0.6;
+const PADDING: f32 = 10.0;
+const TAB: &str = "    ";
+
+/// Lays out highlighted lines as `<text>`/`<tspan>` elements.
+///
+/// The size of the image depends on the longest line, so the body is buffered and written out in [`Render::finish`].
+#[derive(Default)]
+struct Svg {
+    background: bool,
+    /// The background of the image, if it is enabled.
+    bg: Option<Color>,
+    /// The background of the theme. Spans with another background (e.g. from an overlay) get a `<rect>`.
+    theme_bg: Option<Color>,
+    body: String,
+    lines: usize,
+    columns: usize,
+}
+
+impl Render for Svg {
+    fn start(&mut self, theme: &Theme, _out: &mut dyn Write) -> Result<()> {
+        self.theme_bg = theme.settings.background;
+        if self.background {
+            self.bg = self.theme_bg;
+        }
+        Ok(())
+    }
+
+    fn line(
+        &mut self,
+        ranges: &[(Style, &str)],
+        _out: &mut dyn Write,
+    ) -> Result<()> {
+        let y = PADDING + self.lines as f32 * LINE_HEIGHT;
+        let mut col = 0;
+        let mut text = String::new();
+
+        for (style, s) in ranges {
+            let s = s.trim_end_matches(['\n', '\r']).replace('\t', TAB);
+            if s.is_empty() {
+                continue;
+            }
+            let width = text_columns(&s);
+
+            if let Some(bg) = self.theme_bg {
+                if style.background != bg {
+                    self.body.push_str(&format!(
+                        r#"<rect x="{}" y="{y}" width="{}" height="{LINE_HEIGHT}" fill="{}"/>"#,
+                        PADDING + col as f32 * CHAR_WIDTH,
+                        width as f32 * CHAR_WIDTH,
+                        hex_color(style.background),
+                    ));
+                    self.body.push('\n');
+                }
+            }
+
+            // Not every SVG renderer supports `#rrggbbaa`.
+            let fg = blend_fg_color(style.foreground, style.background);
+            text.push_str(&format!(
+                r#"<tspan fill="{}"{}>{}</tspan>"#,
+                hex_color(fg),
+                font_attrs(style.font_style),
+                escape_html(&s),
+            ));
+            col += width;
+        }
+
+        self.body.push_str(&format!(
+            r#"<text x="{PADDING}" y="{}" xml:space="preserve">{text}</text>"#,
+            y + FONT_SIZE,
+        ));
+        self.body.push('\n');
+
+        self.lines += 1;
+        self.columns = self.columns.max(col);
+        Ok(())
+    }
+
+    /// One `<rect>` across the whole width of the image, under the text.
+    fn emphasized_line(
+        &mut self,
+        ranges: &[(Style, &str)],
+        background: Color,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let y = PADDING + self.lines as f32 * LINE_HEIGHT;
+        self.body.push_str(&format!(
+            r#"<rect x="0" y="{y}" width="100%" height="{LINE_HEIGHT}" fill="{}"/>"#,
+            hex_color(background),
+        ));
+        self.body.push('\n');
+        self.line(ranges, out)
+    }
+
+    fn finish(&mut self, out: &mut dyn Write) -> Result<()> {
+        let width = PADDING * 2.0 + self.columns as f32 * CHAR_WIDTH;
+        let height = PADDING * 2.0 + self.lines as f32 * LINE_HEIGHT;
+
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="monospace" font-size="{FONT_SIZE}">"#
+        )?;
+        if let Some(bg) = self.bg {
+            writeln!(
+                out,
+                r#"<rect width="100%" height="100%" fill="{}"/>"#,
+                hex_color(bg)
+            )?;
+        }
+        out.write_all(self.body.as_bytes())?;
+        Ok(out.write_all(b"</svg>\n")?)
+    }
+
+    fn plain(&mut self, contents: &str, out: &mut dyn Write) -> Result<()> {
+        let style = Style {
+            foreground: Color::BLACK,
+            ..Default::default()
+        };
+        for line in LinesWithEndings::from(contents) {
+            self.line(&[(style, line)], out)?;
+        }
+        self.finish(out)
+    }
+}
+
+fn font_attrs(font_style: FontStyle) -> String {
+    let mut attrs = String::new();
+    if font_style.contains(FontStyle::BOLD) {
+        attrs.push_str(r#" font-weight="bold""#);
+    }
+    if font_style.contains(FontStyle::ITALIC) {
+        attrs.push_str(r#" font-style="italic""#);
+    }
+    if font_style.contains(FontStyle::UNDERLINE) {
+        attrs.push_str(r#" text-decoration="underline""#);
+    }
+    attrs
+}
+
+/// Approximates how many monospace cells `s` occupies. East Asian wide characters and emoji take two cells.
+fn text_columns(s: &str) -> usize {
+    s.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115F
+            | 0x2E80..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Writes syntax-highlighted code as an SVG image to either standard output or a provided writer.
+///
+/// Each line becomes a `<text>` element, and each highlighted token a `<tspan>` with the foreground color and font style of the theme. If the background of `style` is enabled, the image is filled with the background color of the theme.
+///
+/// # Example
+///
+/// ```no_run
+/// use hlight::{gen_svg_highlight, theme::theme_ayu_dark, HighLightRes};
+/// use std::fs::File;
+///
+/// let s = r#"
+/// [main]
+/// enabled = false
+/// "😎" = "🍥"
+/// float = nan
+/// "#;
+///
+/// let mut res = HighLightRes::default();
+/// *res.get_name_mut() = theme_ayu_dark();
+///
+/// let mut file = File::create("toml.svg").expect("Failed to create toml.svg");
+/// gen_svg_highlight("toml", s, Some(&res), Some(&mut file))
+///     .expect("Failed to get highlighted svg");
+/// ```
+pub fn gen_svg_highlight(
+    dst_fmt: &str,
+    contents: &str,
+    style: Option<&HighLightRes>,
+    writer: Option<&mut dyn Write>,
+) -> Result<()> {
+    let mut svg = Svg {
+        background: style.is_some_and(|s| *s.get_background()),
+        ..Default::default()
+    };
+    render_highlight(dst_fmt, contents, style, writer, &mut svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    const S: &str = r#"[main]
+enabled = false
+"😎" = "<🍥>"
+"#;
+
+    fn to_svg(res: Option<&HighLightRes>) -> Result<String> {
+        let mut v = Vec::new();
+        gen_svg_highlight("toml", S, res, Some(&mut v))?;
+        Ok(String::from_utf8(v).expect("Invalid UTF-8"))
+    }
+
+    #[test]
+    fn svg() -> Result<()> {
+        let svg = to_svg(Some(&HighLightRes::default()))?;
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains(r#"<rect width="100%" height="100%""#));
+        assert_eq!(svg.matches("<text ").count(), 3);
+        assert!(svg.contains("&lt;🍥&gt;"));
+        Ok(())
+    }
+
+    #[test]
+    fn svg_without_background() -> Result<()> {
+        let res = HighLightRes::default().with_background(false);
+        let svg = to_svg(Some(&res))?;
+        assert!(!svg.contains("<rect"));
+        Ok(())
+    }
+
+    #[test]
+    fn emphasized_lines() -> Result<()> {
+        let res = HighLightRes::default()
+            .with_background(false)
+            .with_emphasized_lines([2]);
+        let svg = to_svg(Some(&res))?;
+
+        let rects = svg
+            .lines()
+            .filter(|x| x.starts_with("<rect"))
+            .collect::<Vec<_>>();
+        assert_eq!(rects.len(), 1);
+        assert!(rects[0].contains(&format!(
+            r#"y="{}" width="100%""#,
+            PADDING + LINE_HEIGHT
+        )));
+        Ok(())
+    }
+
+    #[test]
+    fn translucent_foreground() -> Result<()> {
+        let style = Style {
+            foreground: Color {
+                r: 0xff,
+                g: 0xff,
+                b: 0xff,
+                a: 0x80,
+            },
+            background: Color::BLACK,
+            font_style: FontStyle::empty(),
+        };
+        let mut svg = Svg::default();
+        svg.line(&[(style, "a\n")], &mut io::sink())?;
+
+        assert!(svg.body.contains(r##"<tspan fill="#808080">a</tspan>"##));
+        Ok(())
+    }
+
+    #[test]
+    fn columns() {
+        assert_eq!(text_columns("ab"), 2);
+        assert_eq!(text_columns("\"😎\""), 4);
+    }
+}
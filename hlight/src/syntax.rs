@@ -1,13 +1,21 @@
-use crate::{resource::HighLightRes, theme::READ_DUMP_DATA_ERR};
+use crate::{
+    dump::dump_options,
+    error::{Error, LoadError},
+    resource::HighLightRes,
+    theme::{closest_name, ThemeSet, READ_DUMP_DATA_ERR},
+};
+use bincode::Options;
 use once_cell::sync::OnceCell;
 
-#[cfg(feature = "preset-syntax-set")]
-use syntect::dumps;
+mod detect;
+mod resolver;
+mod sniff;
 
+pub use detect::{detect_syntax, Detection, Evidence};
+pub use resolver::{Fallback, Reason, Resolution, SyntaxResolver};
+pub use sniff::sniff_syntax;
 pub use syntect::parsing::{SyntaxReference, SyntaxSet};
 
-type OnceSyntax = OnceCell<&'static SyntaxReference>;
-
 #[cfg(feature = "preset-syntax-set")]
 const SUBLIME_SYNTAXES: &[u8] = include_bytes!(concat!(
     env!("CARGO_MANIFEST_DIR"),
@@ -25,22 +33,56 @@ const SUBLIME_SYNTAXES: &[u8] = include_bytes!(concat!(
 ///
 /// const SYNTAXES: &[u8] = include_bytes!(concat!(
 ///     env!("CARGO_MANIFEST_DIR"),
-///     "/assets/syntax-set.packdump"
+///     "/assets/theme-syntax-set/syntax-set.packdump"
 /// ));
 ///
 /// let set = load_syntax_set(Some(SYNTAXES));
 /// ```
+///
+/// # Panics
+///
+/// Panics if the data is not an uncompressed syntax set dump. Use [`try_load_syntax_set`] to handle it.
 pub fn load_syntax_set(set: Option<&[u8]>) -> SyntaxSet {
-    let msg = READ_DUMP_DATA_ERR;
+    try_load_syntax_set(set)
+        .unwrap_or_else(|e| panic!("{READ_DUMP_DATA_ERR}: {e}"))
+}
 
-    match set {
-        Some(x) => dumps::from_uncompressed_data(x).expect(msg),
+/// Loads a set of syntaxes, or returns [`Error::Load`] if the data is corrupted, compressed or a dump of a theme set.
+///
+/// If the parameter is None, the default syntax set is used.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{syntax::try_load_syntax_set, Error};
+///
+/// let data = std::fs::read("/path/to/syntax-set.packdump").unwrap_or_default();
+///
+/// let set = match try_load_syntax_set(Some(&data)) {
+///     Ok(set) => set,
+///     Err(Error::Load(e)) => {
+///         eprintln!("{e}, fall back to the preset");
+///         try_load_syntax_set(None).expect("Invalid preset")
+///     }
+///     Err(e) => panic!("{e}"),
+/// };
+/// ```
+pub fn try_load_syntax_set(set: Option<&[u8]>) -> Result<SyntaxSet, Error> {
+    let data = match set {
+        Some(x) => x,
         #[cfg(feature = "preset-syntax-set")]
-        _ => dumps::from_uncompressed_data(set.unwrap_or(SUBLIME_SYNTAXES))
-            .expect(msg),
+        _ => SUBLIME_SYNTAXES,
         #[allow(unreachable_patterns)]
-        _ => SyntaxSet::default(),
-    }
+        _ => return Ok(SyntaxSet::default()),
+    };
+
+    dump_options(data).deserialize(data).map_err(|e| {
+        let found = dump_options(data)
+            .deserialize::<ThemeSet>(data)
+            .is_ok()
+            .then_some("ThemeSet");
+        LoadError::new(data, e, "SyntaxSet", found).into()
+    })
 }
 
 impl<'name> HighLightRes<'name> {
@@ -71,7 +113,13 @@ impl<'name> HighLightRes<'name> {
 
 /// It matches the format string against known syntax formats(e.g. md, toml, json, yaml), returning a reference to the corresponding syntax if found.
 ///
-/// If the format string does not match any known formats, it uses a generic function to find a syntax matching the format string.
+/// If the format string does not match any known formats, it uses a generic function to find a syntax matching the format string. If that fails too, it falls back to JSON.
+///
+/// The known formats are the aliases of [`SyntaxResolver::default`]. To register your own, use [`SyntaxResolver`] directly or [`HighLightRes::with_syntax_resolver`].
+///
+/// # Panics
+///
+/// Panics if the format is unknown and the set contains neither JSON nor a "Plain Text" syntax. The preset syntax set contains both, so use [`SyntaxResolver::resolve`] for other sets.
 ///
 /// # Example
 ///
@@ -86,36 +134,78 @@ pub fn match_static_syntax(
     set: &'static SyntaxSet,
     fmt: &str,
 ) -> &'static SyntaxReference {
-    match fmt {
-        "md" | "markdown" => get_markdown(set),
-        "toml" => get_toml(set),
-        "yaml" | "yml" => get_yaml(set),
-        "json" | "json5" | "ron" => get_json(set),
-        "pwsh" | "ps1" | "powershell" => get_pwsh(set),
-        _ => find_syntax(set, fmt),
+    match SyntaxResolver::builtin().resolve(set, fmt) {
+        Ok(x) => x.get_syntax(),
+        // Neither JSON nor plain text exists in the set, so this panics.
+        Err(_) => set.find_syntax_plain_text(),
     }
 }
 
-/// Finds and returns the appropriate syntax highlighting definition from a `SyntaxSet` based on a given destination format. If not found, it will fallback to json.
-pub(crate) fn find_syntax<'a>(
+/// Like [`match_static_syntax`], but if the format string is empty, the syntax is sniffed from `contents` (see [`sniff_syntax`]) before falling back to JSON.
+///
+/// # Panics
+///
+/// Panics in the same case as [`match_static_syntax`]. Use [`SyntaxResolver::resolve_or_sniff`] to get an error instead.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{syntax::match_static_syntax_or_sniff, HighLightRes};
+///
+/// let set = HighLightRes::static_syntax_set();
+/// let syntax = match_static_syntax_or_sniff(set, "", "[main]\nenabled = false\n");
+/// assert_eq!(syntax.name, "TOML");
+/// ```
+pub fn match_static_syntax_or_sniff(
+    set: &'static SyntaxSet,
+    fmt: &str,
+    contents: &str,
+) -> &'static SyntaxReference {
+    match SyntaxResolver::builtin().resolve_or_sniff(set, fmt, contents) {
+        Ok(x) => x.get_syntax(),
+        // Panics, see above.
+        Err(_) => set.find_syntax_plain_text(),
+    }
+}
+
+/// Finds a syntax by file extension, then by name.
+///
+/// Unlike [`match_static_syntax`], it does not fall back to another syntax, but returns [`Error::UnknownSyntax`] with the closest name or extension.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{syntax::try_find_syntax, Error, HighLightRes};
+///
+/// let set = HighLightRes::static_syntax_set();
+/// assert_eq!(try_find_syntax(set, "yml").map(|s| &s.name[..]).ok(), Some("YAML"));
+///
+/// let err = try_find_syntax(set, "powershel").unwrap_err();
+/// assert!(matches!(
+///     err,
+///     Error::UnknownSyntax { suggestion: Some(s), .. } if s == "PowerShell"
+/// ));
+/// ```
+pub fn try_find_syntax<'a>(
     set: &'a SyntaxSet,
-    dst_fmt: &str,
-) -> &'a SyntaxReference {
-    set.find_syntax_by_extension(dst_fmt)
-        .unwrap_or_else(|| {
-            set.find_syntax_by_name(dst_fmt)
-                .unwrap_or_else(|| {
-                    let to_json = || set.find_syntax_by_extension("json");
-
-                    match dst_fmt {
-                        "sexp" | "lexpr" => set
-                            .find_syntax_by_extension("lisp")
-                            .or_else(to_json),
-                        _ => to_json(),
-                    }
-                    .unwrap_or_else(|| set.find_syntax_plain_text())
-                })
-        })
+    fmt: &str,
+) -> Result<&'a SyntaxReference, Error> {
+    set.find_syntax_by_extension(fmt)
+        .or_else(|| set.find_syntax_by_name(fmt))
+        .ok_or_else(|| unknown_syntax(set, fmt))
+}
+
+/// [`Error::UnknownSyntax`] with the closest name or extension in `set`.
+pub(crate) fn unknown_syntax(set: &SyntaxSet, fmt: &str) -> Error {
+    let candidates = set.syntaxes().iter().flat_map(|s| {
+        let ext = s.file_extensions.iter().map(String::as_str);
+        std::iter::once(s.name.as_str()).chain(ext)
+    });
+
+    Error::UnknownSyntax {
+        name: fmt.to_owned(),
+        suggestion: closest_name(fmt, candidates).map(ToOwned::to_owned),
+    }
 }
 
 /// Finds syntax reference by name.
@@ -138,37 +228,111 @@ pub fn find_syntax_name<'a>(set: &'a SyntaxSet, name: &str) -> &'a SyntaxReferen
         })
 }
 
-// static SyntaxReference:
-// md, json, yaml, toml
-//
-fn get_markdown(set: &'static SyntaxSet) -> &'static SyntaxReference {
-    static S: OnceSyntax = OnceCell::new();
-    S.get_or_init(|| find_syntax_name(set, "Markdown"))
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HighLightRes;
 
-fn get_json(set: &'static SyntaxSet) -> &'static SyntaxReference {
-    static S: OnceSyntax = OnceCell::new();
-    S.get_or_init(|| find_syntax_name(set, "JSON"))
-}
+    #[test]
+    fn load_invalid_dump() {
+        const THEMES: &[u8] = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/theme-syntax-set/theme-set.packdump"
+        ));
 
-fn get_yaml(set: &'static SyntaxSet) -> &'static SyntaxReference {
-    static S: OnceSyntax = OnceCell::new();
-    S.get_or_init(|| find_syntax_name(set, "YAML"))
-}
+        let load = |data| match try_load_syntax_set(Some(data)) {
+            Err(Error::Load(e)) => e,
+            _ => panic!("The data should be invalid"),
+        };
+
+        assert!(matches!(
+            load(THEMES),
+            LoadError::WrongSetType {
+                expected: "SyntaxSet",
+                found: "ThemeSet"
+            }
+        ));
+        assert!(matches!(
+            load(&[0x78, 0x9C, 1, 2, 3]),
+            LoadError::Compressed
+        ));
+        assert!(matches!(load(&[1, 2, 3]), LoadError::InvalidData(_)));
+    }
 
-fn get_toml(set: &'static SyntaxSet) -> &'static SyntaxReference {
-    static S: OnceSyntax = OnceCell::new();
-    S.get_or_init(|| find_syntax_name(set, "TOML"))
+    #[test]
+    fn data_formats() -> crate::Result<()> {
+        let res = HighLightRes::default();
+        let scopes = |fmt, s| -> crate::Result<Vec<(String, String)>> {
+            let mut spans = Vec::new();
+            for line in res.scoped_tokens(fmt, s)? {
+                for (stack, text) in line? {
+                    let stack = stack
+                        .as_slice()
+                        .iter()
+                        .map(|x| x.build_string())
+                        .collect::<Vec<_>>();
+                    spans.push((stack.join(" "), text.to_owned()));
+                }
+            }
+            Ok(spans)
+        };
+        let scope_of = |spans: &[(String, String)], text: &str| {
+            spans
+                .iter()
+                .find(|(_, t)| t == text)
+                .map(|(scope, _)| scope.clone())
+                .unwrap_or_default()
+        };
+
+        let json5 = "{
+  // comment
+  key: 'single',
+  hex: 0xFF,
+  n: [.5, +Infinity,],
 }
+";
+        let spans = scopes("json5", json5)?;
+        assert!(spans
+            .iter()
+            .all(|(scope, _)| scope.starts_with("source.json5")));
+        assert!(!spans.iter().any(|(scope, _)| scope.contains("invalid")));
+        assert!(scope_of(&spans, "key").contains("string.unquoted"));
+        assert!(scope_of(&spans, " comment\n").contains("comment.line"));
+        assert!(scope_of(&spans, "FF").contains("constant.numeric"));
+        assert!(scope_of(&spans, "Infinity").contains("constant.language"));
 
-fn get_pwsh(set: &'static SyntaxSet) -> &'static SyntaxReference {
-    static S: OnceSyntax = OnceCell::new();
-    S.get_or_init(|| find_syntax_name(set, "PowerShell"))
+        let jsonc = r#"{
+  "a": 1, // comment
+  "b": [2,],
 }
+"#;
+        let spans = scopes("jsonc", jsonc)?;
+        assert!(spans[0].0.starts_with("source.json.jsonc"));
+        assert!(!spans.iter().any(|(scope, _)| scope.contains("invalid")));
+        assert!(scope_of(&spans, " comment\n").contains("comment.line"));
 
-#[cfg(test)]
-mod tests {
-    use crate::HighLightRes;
+        let spans = scopes("ron", "Config(name: \"ron\", list: [1, 2])\n")?;
+        assert!(spans[0].0.starts_with("source.ron"));
+        assert!(scope_of(&spans, "Config").contains("entity.name.class"));
+
+        let sexp = "(config (name . \"lexpr\") #:key #t) ; comment\n";
+        let spans = scopes("lexpr", sexp)?;
+        assert!(spans[0].0.starts_with("source.sexp"));
+        assert!(!spans.iter().any(|(scope, _)| scope.contains("invalid")));
+        assert!(scope_of(&spans, "#:key").contains("keyword"));
+        assert!(scope_of(&spans, "#t").contains("constant.language"));
+
+        let ini = r#"; comment
+[remote "origin"]
+url = https://example.com
+"#;
+        let spans = scopes("ini", ini)?;
+        assert!(spans[0].0.starts_with("source.ini"));
+        assert!(scope_of(&spans, "remote ").contains("entity.name.section"));
+        assert!(scope_of(&spans, "url").contains("variable.other.key"));
+        assert!(scope_of(&spans, " comment\n").contains("comment.line"));
+        Ok(())
+    }
 
     #[test]
     fn static_set() {
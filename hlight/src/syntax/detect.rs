@@ -0,0 +1,274 @@
+use super::sniff_syntax;
+use crate::resource::HighLightRes;
+use getset::Getters;
+use std::path::Path;
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+/// How many lines at the start and the end of a file may contain a modeline, the same as the default of Vim's `modelines` option.
+const MODELINES: usize = 5;
+
+/// What a syntax was detected from.
+///
+/// The variants are ordered by confidence, from the highest to the lowest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Evidence {
+    /// A Vim or Emacs modeline, e.g. `# vim: ft=toml` or `# -*- mode: yaml -*-`. It is an explicit choice of the author, so it outranks the file name.
+    Modeline,
+    /// The whole file name, e.g. `Cargo.lock` or `.bashrc`.
+    FileName,
+    /// The file extension, e.g. `fish` of `config.fish`.
+    Extension,
+    /// The interpreter of a shebang line, e.g. `#!/usr/bin/env fish`.
+    Shebang,
+    /// The `first_line_match` regex of a syntax definition, e.g. `<?xml` for XML.
+    FirstLine,
+    /// The structure of the contents, see [`sniff_syntax`](super::sniff_syntax).
+    Content,
+}
+
+/// A syntax found by [`detect_syntax`], and what it was detected from.
+#[derive(Getters, Debug, Clone, Copy)]
+#[getset(get = "pub with_prefix")]
+pub struct Detection<'a> {
+    syntax: &'a SyntaxReference,
+    evidence: Evidence,
+}
+
+/// Detects the syntax of a file from its path and contents.
+///
+/// The candidates are ranked by confidence (see [`Evidence`]), and every syntax appears only once, with its most reliable evidence. The result is empty if nothing matches.
+///
+/// The name of a detected syntax can be passed as the format string of [`gen_syntax_highlight`](crate::gen_syntax_highlight) and friends.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{
+///     syntax::{detect_syntax, Evidence},
+///     HighLightRes,
+/// };
+/// use std::path::Path;
+///
+/// let set = HighLightRes::static_syntax_set();
+/// let s = "#!/usr/bin/env fish\nset -x EDITOR vim\n";
+///
+/// let detected = detect_syntax(set, Some(Path::new("config")), s);
+/// let best = detected.first().expect("No syntax was detected");
+///
+/// assert_eq!(best.get_syntax().name, "Fish");
+/// assert_eq!(*best.get_evidence(), Evidence::Shebang);
+/// ```
+pub fn detect_syntax<'a>(
+    set: &'a SyntaxSet,
+    path: Option<&Path>,
+    contents: &str,
+) -> Vec<Detection<'a>> {
+    let contents = contents.trim_start_matches('\u{feff}');
+    let first_line = contents.lines().next().unwrap_or_default();
+    let mut detected = Vec::new();
+    let mut push = |syntax: Option<&'a SyntaxReference>, evidence| {
+        if let Some(syntax) = syntax {
+            detected.push(Detection { syntax, evidence })
+        }
+    };
+
+    for mode in modelines(contents) {
+        push(set.find_syntax_by_token(mode), Evidence::Modeline);
+    }
+    if let Some(path) = path {
+        let file_name = path.file_name().and_then(|x| x.to_str());
+        let extension = path.extension().and_then(|x| x.to_str());
+        push(
+            file_name.and_then(|x| set.find_syntax_by_extension(x)),
+            Evidence::FileName,
+        );
+        push(
+            extension.and_then(|x| set.find_syntax_by_extension(x)),
+            Evidence::Extension,
+        );
+    }
+    push(
+        shebang_interpreter(first_line)
+            .and_then(|x| set.find_syntax_by_token(x)),
+        Evidence::Shebang,
+    );
+    push(
+        set.find_syntax_by_first_line(first_line),
+        Evidence::FirstLine,
+    );
+    push(sniff_syntax(set, contents), Evidence::Content);
+
+    detected.sort_by_key(|x| x.evidence);
+    let mut seen = Vec::new();
+    detected.retain(|x| match seen.contains(&x.syntax.name) {
+        true => false,
+        _ => {
+            seen.push(x.syntax.name.clone());
+            true
+        }
+    });
+    detected
+}
+
+impl<'name> HighLightRes<'name> {
+    /// Detects the syntax of a file with the syntax set of `self`. See [`detect_syntax`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::HighLightRes;
+    /// use std::path::Path;
+    ///
+    /// let res = HighLightRes::default();
+    /// let detected = res.detect_syntax(Some(Path::new("Cargo.lock")), "");
+    /// assert_eq!(detected[0].get_syntax().name, "TOML");
+    /// ```
+    pub fn detect_syntax(
+        &self,
+        path: Option<&Path>,
+        contents: &str,
+    ) -> Vec<Detection<'static>> {
+        detect_syntax(self.get_syntax_set(), path, contents)
+    }
+}
+
+/// The program of a shebang line without the directory and version, e.g. `python` for `#!/usr/bin/env -S python3.11 -u`.
+fn shebang_interpreter(line: &str) -> Option<&str> {
+    fn basename(path: &str) -> &str {
+        path.rsplit('/').next().unwrap_or(path)
+    }
+    let mut args = line.strip_prefix("#!")?.split_whitespace();
+
+    let mut program = basename(args.next()?);
+    if program == "env" {
+        // skips options and variable assignments, e.g. `env -S LANG=C sh`
+        program =
+            basename(args.find(|x| !x.starts_with('-') && !x.contains('='))?);
+    }
+    let program =
+        program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    (!program.is_empty()).then_some(program)
+}
+
+/// The file types set by Vim or Emacs modelines in the first and last lines of `contents`.
+fn modelines(contents: &str) -> Vec<&str> {
+    let lines = contents.lines().collect::<Vec<_>>();
+    let tail = lines.len().saturating_sub(MODELINES).max(MODELINES);
+
+    lines
+        .iter()
+        .take(MODELINES)
+        .chain(lines.iter().skip(tail))
+        .filter_map(|line| vim_modeline(line).or_else(|| emacs_modeline(line)))
+        .collect()
+}
+
+/// `vim: set ft=toml :`, `vi:syntax=yaml` or `ex: filetype=sh`
+fn vim_modeline(line: &str) -> Option<&str> {
+    let start = ["vim:", "Vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(i, _)| line[..*i].ends_with(char::is_whitespace) || *i == 0)
+            .map(|(i, _)| i + marker.len())
+    })?;
+
+    line[start..]
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .find_map(|option| {
+            let (key, value) = option.split_once('=')?;
+            matches!(key, "ft" | "filetype" | "syn" | "syntax")
+                .then_some(value)
+                .filter(|x| !x.is_empty())
+        })
+}
+
+/// `-*- mode: yaml -*-`, `-*- yaml -*-` or `-*- coding: utf-8; mode: toml -*-`
+fn emacs_modeline(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (vars, _) = rest.split_once("-*-")?;
+
+    let mode = match vars.contains(':') {
+        true => vars.split(';').find_map(|var| {
+            let (key, value) = var.split_once(':')?;
+            (key.trim().eq_ignore_ascii_case("mode")).then_some(value)
+        })?,
+        _ => vars,
+    };
+    let mode = mode.trim();
+    let mode = mode.strip_suffix("-mode").unwrap_or(mode);
+    (!mode.is_empty()).then_some(mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HighLightRes;
+
+    fn detect(path: Option<&str>, contents: &str) -> Vec<(String, Evidence)> {
+        let set = HighLightRes::static_syntax_set();
+        detect_syntax(set, path.map(Path::new), contents)
+            .into_iter()
+            .map(|x| (x.syntax.name.clone(), x.evidence))
+            .collect()
+    }
+
+    #[test]
+    fn path() {
+        let name = |path| detect(Some(path), "")[0].clone();
+
+        assert_eq!(name("Cargo.lock"), ("TOML".into(), Evidence::FileName));
+        assert_eq!(name("/home/user/.bashrc").1, Evidence::FileName);
+        assert_eq!(name("config.fish"), ("Fish".into(), Evidence::Extension));
+        assert_eq!(name("data.JSON5").0, "JSON5");
+        assert!(detect(Some("README"), "").is_empty());
+    }
+
+    #[test]
+    fn shebang() {
+        assert_eq!(shebang_interpreter("#!/bin/sh -e"), Some("sh"));
+        assert_eq!(
+            shebang_interpreter("#!/usr/bin/env -S LANG=C python3.11 -u"),
+            Some("python")
+        );
+        assert_eq!(shebang_interpreter("#! /usr/bin/env"), None);
+        assert_eq!(shebang_interpreter("# comment"), None);
+
+        let detected = detect(None, "#!/usr/bin/env elvish\necho 1\n");
+        assert_eq!(detected, [("Elvish".into(), Evidence::Shebang)]);
+    }
+
+    #[test]
+    fn modeline() {
+        assert_eq!(vim_modeline("# vim: set ft=toml :"), Some("toml"));
+        assert_eq!(vim_modeline("// vi:ts=4:syntax=yaml"), Some("yaml"));
+        assert_eq!(vim_modeline("let svim:ft=x"), None);
+        assert_eq!(emacs_modeline("# -*- mode: yaml -*-"), Some("yaml"));
+        assert_eq!(
+            emacs_modeline(";; -*- coding: utf-8; mode: lisp-mode -*-"),
+            Some("lisp")
+        );
+        assert_eq!(emacs_modeline("# -*- Markdown -*-"), Some("Markdown"));
+
+        // The modeline outranks the extension.
+        let s = "a = 1\n\n\n\n\n\n# vim: ft=yaml\n";
+        let detected = detect(Some("config.toml"), s);
+        assert_eq!(
+            detected,
+            [
+                ("YAML".into(), Evidence::Modeline),
+                ("TOML".into(), Evidence::Extension)
+            ]
+        );
+    }
+
+    #[test]
+    fn first_line() {
+        let s = "<?xml version=\"1.0\"?>\n<a/>\n";
+        assert_eq!(detect(None, s), [("XML".into(), Evidence::FirstLine)]);
+
+        // The shebang and the first line regex of bash find the same syntax.
+        let detected = detect(None, "#!/bin/bash\necho 1\n");
+        assert_eq!(detected.len(), 1);
+        assert_eq!(detected[0].1, Evidence::Shebang);
+    }
+}
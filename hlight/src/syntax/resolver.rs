@@ -0,0 +1,490 @@
+use super::{sniff_syntax, unknown_syntax};
+use crate::{error::Error, resource::HighLightRes};
+use getset::Getters;
+use once_cell::sync::OnceCell;
+use std::collections::BTreeMap;
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+/// The built-in aliases: (alias, syntax name)
+const BUILTIN_ALIASES: &[(&str, &str)] = &[
+    ("md", "Markdown"),
+    ("markdown", "Markdown"),
+    ("toml", "TOML"),
+    ("yaml", "YAML"),
+    ("yml", "YAML"),
+    ("json", "JSON"),
+    ("json5", "JSON5"),
+    ("jsonc", "JSONC"),
+    ("ron", "RON"),
+    ("sexp", "S-Expression"),
+    ("lexpr", "S-Expression"),
+    ("pwsh", "PowerShell"),
+    ("ps1", "PowerShell"),
+    ("powershell", "PowerShell"),
+];
+
+/// What to do if a format string cannot be resolved to a syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Fallback {
+    /// Plain text, i.e. no highlighting.
+    PlainText,
+    /// The first of these syntax names or file extensions that exists in the syntax set, else plain text.
+    Syntax(Vec<String>),
+    /// Returns [`Error::UnknownSyntax`] with the closest syntax name or extension.
+    Error,
+}
+
+impl Default for Fallback {
+    /// Plain text, so that e.g. Rust code is not colored as JSON. Previous versions fell back to JSON, i.e. `Fallback::Syntax(vec!["JSON".into()])`.
+    fn default() -> Self {
+        Self::PlainText
+    }
+}
+
+/// Why a syntax was chosen by [`SyntaxResolver`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Reason {
+    /// An alias registered in the resolver, e.g. `md` for Markdown.
+    Alias,
+    /// The format string is a file extension of the syntax, e.g. `rs` for Rust.
+    Extension,
+    /// The format string is the name of the syntax, e.g. `Rust`.
+    Name,
+    /// The format string is empty, and the syntax was sniffed from the contents. See [`sniff_syntax`].
+    Content,
+    /// Nothing matched, so the [`Fallback`] was used.
+    Fallback,
+}
+
+/// The syntax chosen by [`SyntaxResolver`], and why.
+#[derive(Getters, Debug, Clone, Copy)]
+#[getset(get = "pub with_prefix")]
+pub struct Resolution<'a> {
+    syntax: &'a SyntaxReference,
+    reason: Reason,
+}
+
+/// Resolves format strings (e.g. `"md"`) to syntaxes of a syntax set.
+///
+/// A format string is resolved in this order:
+///
+/// 1. The alias chain registered for it (case-insensitive). Every target is a syntax name or a file extension, and the first one that exists in the syntax set wins.
+/// 2. A syntax with the format string as file extension, then as name.
+/// 3. Only with [`SyntaxResolver::resolve_or_sniff`] and an empty format string: the syntax sniffed from the contents. See [`sniff_syntax`] and [`SyntaxResolver::with_sniffing`].
+/// 4. The [`Fallback`], plain text by default.
+///
+/// A non-empty format string that is unknown always ends up at the [`Fallback`], so that e.g. a Python file is not sniffed as some config format.
+///
+/// The default resolver has the built-in aliases of hlight, e.g. `md` for Markdown and `ps1` for PowerShell. Since the targets are looked up by name, the aliases also work with custom syntax sets, see [`load_syntax_set`](super::load_syntax_set).
+///
+/// # Example
+///
+/// ```
+/// use hlight::{
+///     syntax::{Fallback, Reason, SyntaxResolver},
+///     HighLightRes,
+/// };
+///
+/// let resolver = SyntaxResolver::default()
+///     // "Nushell" does not exist in the preset, so it falls back to "sh".
+///     .with_alias("nu", ["Nushell", "sh"])
+///     .with_fallback(Fallback::PlainText);
+///
+/// let set = HighLightRes::static_syntax_set();
+/// let nu = resolver.resolve(set, "NU")?;
+/// assert_eq!(nu.get_syntax().name, "Bourne Again Shell (bash)");
+/// assert_eq!(*nu.get_reason(), Reason::Alias);
+///
+/// let unknown = resolver.resolve(set, "unknown")?;
+/// assert_eq!(unknown.get_syntax().name, "Plain Text");
+/// assert_eq!(*unknown.get_reason(), Reason::Fallback);
+///
+/// let res = HighLightRes::default().with_syntax_resolver(resolver);
+/// # Ok::<(), hlight::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxResolver {
+    /// lowercase alias -> the names or extensions to try
+    aliases: BTreeMap<String, Vec<String>>,
+    fallback: Fallback,
+    sniffing: bool,
+}
+
+impl Default for SyntaxResolver {
+    fn default() -> Self {
+        let resolver = Self::new().with_fallback(Fallback::default());
+        BUILTIN_ALIASES
+            .iter()
+            .fold(resolver, |r, (alias, name)| r.with_alias(*alias, [*name]))
+    }
+}
+
+impl SyntaxResolver {
+    /// Creates a resolver without any aliases, falling back to plain text. Use [`SyntaxResolver::default`] for the built-in aliases.
+    pub fn new() -> Self {
+        Self {
+            aliases: BTreeMap::new(),
+            fallback: Fallback::PlainText,
+            sniffing: true,
+        }
+    }
+
+    /// The default resolver, but falling back to JSON as in previous versions. It is shared by [`match_static_syntax`](super::match_static_syntax).
+    pub(crate) fn builtin() -> &'static Self {
+        static S: OnceCell<SyntaxResolver> = OnceCell::new();
+        S.get_or_init(|| {
+            Self::default().with_fallback(Fallback::Syntax(vec!["JSON".into()]))
+        })
+    }
+
+    /// Registers an alias with a chain of syntax names or file extensions, replacing a previous one with the same alias.
+    pub fn with_alias<A, I, S>(mut self, alias: A, targets: I) -> Self
+    where
+        A: AsRef<str>,
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.register_alias(alias, targets);
+        self
+    }
+
+    /// Like [`SyntaxResolver::with_alias`], but in place, e.g. through [`HighLightRes::get_syntax_resolver_mut`](crate::HighLightRes::get_syntax_resolver_mut).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::HighLightRes;
+    ///
+    /// let mut res = HighLightRes::default();
+    /// res.get_syntax_resolver_mut().register_alias("conf", ["INI"]);
+    /// ```
+    pub fn register_alias<A, I, S>(&mut self, alias: A, targets: I)
+    where
+        A: AsRef<str>,
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.aliases.insert(
+            alias.as_ref().to_lowercase(),
+            targets.into_iter().map(Into::into).collect(),
+        );
+    }
+
+    /// Removes an alias, e.g. a built-in one. Returns its targets.
+    pub fn remove_alias(&mut self, alias: &str) -> Option<Vec<String>> {
+        self.aliases.remove(&alias.to_lowercase())
+    }
+
+    /// Sets what to do if a format string cannot be resolved. The default resolver falls back to plain text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::{
+    ///     syntax::{Fallback, SyntaxResolver},
+    ///     Error, HighLightRes,
+    /// };
+    ///
+    /// let set = HighLightRes::static_syntax_set();
+    /// let resolver = SyntaxResolver::default().with_fallback(Fallback::Error);
+    ///
+    /// let err = resolver.resolve(set, "tomll").unwrap_err();
+    /// assert!(matches!(
+    ///     err,
+    ///     Error::UnknownSyntax { suggestion: Some(s), .. } if s == "TOML"
+    /// ));
+    /// ```
+    pub fn with_fallback(self, fallback: Fallback) -> Self {
+        Self { fallback, ..self }
+    }
+
+    /// What to do if a format string cannot be resolved.
+    pub fn fallback(&self) -> &Fallback {
+        &self.fallback
+    }
+
+    /// Whether an empty format string is sniffed from the contents before falling back. It is enabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::{
+    ///     syntax::{Fallback, SyntaxResolver},
+    ///     HighLightRes,
+    /// };
+    ///
+    /// let set = HighLightRes::static_syntax_set();
+    /// let resolver = SyntaxResolver::default().with_fallback(Fallback::PlainText);
+    ///
+    /// let sniffed = resolver.resolve_or_sniff(set, "", "a = 1\n")?;
+    /// assert_eq!(sniffed.get_syntax().name, "TOML");
+    ///
+    /// let resolver = resolver.with_sniffing(false);
+    /// let plain = resolver.resolve_or_sniff(set, "", "a = 1\n")?;
+    /// assert_eq!(plain.get_syntax().name, "Plain Text");
+    /// # Ok::<(), hlight::Error>(())
+    /// ```
+    pub fn with_sniffing(self, switch: bool) -> Self {
+        Self {
+            sniffing: switch,
+            ..self
+        }
+    }
+
+    /// The registered aliases (lowercase) and their targets.
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.aliases.iter().map(|(k, v)| (k.as_str(), v.as_slice()))
+    }
+
+    /// Resolves `fmt` by its alias chain, then as a file extension or a syntax name. Returns `None` instead of falling back.
+    pub fn lookup<'a>(
+        &self,
+        set: &'a SyntaxSet,
+        fmt: &str,
+    ) -> Option<Resolution<'a>> {
+        let found = |syntax, reason| Some(Resolution { syntax, reason });
+
+        if let Some(x) = self
+            .aliases
+            .get(&fmt.to_lowercase())
+            .and_then(|targets| find_first(set, targets))
+        {
+            return found(x, Reason::Alias);
+        }
+        if let Some(x) = set.find_syntax_by_extension(fmt) {
+            return found(x, Reason::Extension);
+        }
+        found(set.find_syntax_by_name(fmt)?, Reason::Name)
+    }
+
+    /// Resolves `fmt`, or applies the [`Fallback`].
+    ///
+    /// Returns [`Error::UnknownSyntax`] if the fallback is [`Fallback::Error`], or if the syntax set does not contain plain text.
+    pub fn resolve<'a>(
+        &self,
+        set: &'a SyntaxSet,
+        fmt: &str,
+    ) -> Result<Resolution<'a>, Error> {
+        self.lookup(set, fmt)
+            .map_or_else(|| self.fall_back(set, fmt), Ok)
+    }
+
+    /// Like [`SyntaxResolver::resolve`], but an empty format string is sniffed from `contents` before falling back, unless sniffing is disabled by [`SyntaxResolver::with_sniffing`].
+    pub fn resolve_or_sniff<'a>(
+        &self,
+        set: &'a SyntaxSet,
+        fmt: &str,
+        contents: &str,
+    ) -> Result<Resolution<'a>, Error> {
+        self.lookup(set, fmt)
+            .or_else(|| {
+                if !self.sniffing || !fmt.trim().is_empty() {
+                    return None;
+                }
+                log::debug!("No format, sniffing the contents");
+                let syntax = sniff_syntax(set, contents)?;
+                Some(Resolution {
+                    syntax,
+                    reason: Reason::Content,
+                })
+            })
+            .map_or_else(|| self.fall_back(set, fmt), Ok)
+    }
+
+    fn fall_back<'a>(
+        &self,
+        set: &'a SyntaxSet,
+        fmt: &str,
+    ) -> Result<Resolution<'a>, Error> {
+        let plain_text = || set.find_syntax_by_name("Plain Text");
+
+        let syntax = match &self.fallback {
+            Fallback::PlainText => plain_text(),
+            Fallback::Syntax(targets) => {
+                find_first(set, targets).or_else(plain_text)
+            }
+            Fallback::Error => None,
+        }
+        .ok_or_else(|| unknown_syntax(set, fmt))?;
+
+        log::info!("Unknown format {fmt:?}, falling back to {}", syntax.name);
+        Ok(Resolution {
+            syntax,
+            reason: Reason::Fallback,
+        })
+    }
+}
+
+impl<'name> HighLightRes<'name> {
+    /// Resolves the syntax that highlights `contents` as `dst_fmt`, and reports why it was chosen. See [`SyntaxResolver`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::{
+    ///     syntax::{Fallback, Reason, SyntaxResolver},
+    ///     HighLightRes,
+    /// };
+    ///
+    /// let resolver = SyntaxResolver::default().with_fallback(Fallback::PlainText);
+    /// let res = HighLightRes::default().with_syntax_resolver(resolver);
+    ///
+    /// let resolved = res.resolve_syntax("rs", "fn main() {}\n")?;
+    /// assert_eq!(resolved.get_syntax().name, "Plain Text");
+    /// assert_eq!(*resolved.get_reason(), Reason::Fallback);
+    /// # Ok::<(), hlight::Error>(())
+    /// ```
+    pub fn resolve_syntax(
+        &self,
+        dst_fmt: &str,
+        contents: &str,
+    ) -> Result<Resolution<'static>, Error> {
+        self.get_syntax_resolver().resolve_or_sniff(
+            self.get_syntax_set(),
+            dst_fmt,
+            contents,
+        )
+    }
+}
+
+/// The first target that exists in `set`, by name or file extension.
+fn find_first<'a>(
+    set: &'a SyntaxSet,
+    targets: &[String],
+) -> Option<&'a SyntaxReference> {
+    targets.iter().find_map(|x| {
+        set.find_syntax_by_name(x)
+            .or_else(|| set.find_syntax_by_extension(x))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HighLightRes;
+
+    fn name(r: &SyntaxResolver, set: &SyntaxSet, fmt: &str) -> String {
+        match r.resolve(set, fmt) {
+            Ok(x) => x.syntax.name.clone(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn aliases() {
+        let set = HighLightRes::static_syntax_set();
+        let name = |r: &SyntaxResolver, fmt: &str| name(r, set, fmt);
+        let default = SyntaxResolver::default();
+
+        assert_eq!(name(&default, "md"), "Markdown");
+        assert_eq!(name(&default, "PS1"), "PowerShell");
+        assert_eq!(name(&default, "lexpr"), "S-Expression");
+        assert_eq!(name(&default, "fish"), "Fish");
+        assert_eq!(name(&default, "unknown"), "Plain Text");
+
+        let mut custom = default.clone().with_alias("md", ["XML"]);
+        custom.register_alias("cfg", ["missing", "ini"]);
+        assert_eq!(name(&custom, "md"), "XML");
+        assert_eq!(name(&custom, "cfg"), "INI");
+
+        assert!(custom.remove_alias("md").is_some());
+        assert_eq!(name(&custom, "md"), "Markdown");
+
+        let empty = SyntaxResolver::new();
+        assert_eq!(empty.aliases().count(), 0);
+        assert_eq!(name(&empty, "pwsh"), "PowerShell");
+        assert_eq!(name(&empty, "powershell"), "PowerShell");
+        assert_eq!(name(&empty, "unknown"), "Plain Text");
+    }
+
+    #[test]
+    fn reasons() -> crate::Result<()> {
+        let set = HighLightRes::static_syntax_set();
+        let resolver = SyntaxResolver::default();
+        let reason = |fmt, contents| {
+            resolver
+                .resolve_or_sniff(set, fmt, contents)
+                .map(|x| (x.syntax.name.clone(), x.reason))
+        };
+
+        assert_eq!(reason("yml", "")?, ("YAML".into(), Reason::Alias));
+        assert_eq!(reason("XML", "")?, ("XML".into(), Reason::Extension));
+        let bash = "Bourne Again Shell (bash)";
+        assert_eq!(reason(bash, "")?, (bash.into(), Reason::Name));
+        assert_eq!(reason("", "a = 1\n")?, ("TOML".into(), Reason::Content));
+        // There is no syntax for Rust in the preset.
+        let plain = "Plain Text";
+        assert_eq!(reason("rs", "")?, (plain.into(), Reason::Fallback));
+        // Only an empty format string is sniffed.
+        assert_eq!(reason("py", "a = 1\n")?, (plain.into(), Reason::Fallback));
+
+        let resolver = resolver.with_sniffing(false);
+        let name = resolver.resolve_or_sniff(set, "", "a = 1\n")?.syntax;
+        assert_eq!(name.name, plain);
+
+        // The legacy functions still fall back to JSON.
+        let name = SyntaxResolver::builtin().resolve(set, "rs")?.syntax;
+        assert_eq!(name.name, "JSON");
+        Ok(())
+    }
+
+    #[test]
+    fn fallbacks() {
+        let set = HighLightRes::static_syntax_set();
+        let with = |fallback| SyntaxResolver::default().with_fallback(fallback);
+
+        let plain = with(Fallback::PlainText);
+        assert_eq!(name(&plain, set, "rs"), "Plain Text");
+        assert_eq!(name(&plain, set, "toml"), "TOML");
+
+        let chain = with(Fallback::Syntax(vec!["missing".into(), "sh".into()]));
+        assert_eq!(name(&chain, set, "unknown"), "Bourne Again Shell (bash)");
+
+        let err = with(Fallback::Error).resolve(set, "tomll").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnknownSyntax { name, suggestion: Some(s) }
+                if name == "tomll" && s == "TOML"
+        ));
+    }
+
+    #[test]
+    fn custom_set() -> Result<(), syntect::LoadingError> {
+        // Only INI and plain text
+        let mut builder = syntect::parsing::SyntaxSetBuilder::new();
+        builder.add_plain_text_syntax();
+        builder.add_from_folder(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/syntax/ini"),
+            true,
+        )?;
+        let set = builder.build();
+
+        let resolver = SyntaxResolver::default();
+        assert_eq!(name(&resolver, &set, "cfg"), "INI");
+        assert_eq!(name(&resolver, &set, "md"), "Plain Text");
+
+        let resolver = resolver.with_alias("conf", ["INI"]);
+        assert_eq!(name(&resolver, &set, "conf"), "INI");
+        Ok(())
+    }
+
+    #[test]
+    fn empty_set() {
+        let set = syntect::parsing::SyntaxSetBuilder::new().build();
+
+        // Neither JSON nor plain text exists.
+        let json = Fallback::Syntax(vec!["JSON".into()]);
+        for fallback in [json, Fallback::PlainText] {
+            let resolver = SyntaxResolver::default().with_fallback(fallback);
+            assert!(matches!(
+                resolver.resolve(&set, "md"),
+                Err(Error::UnknownSyntax {
+                    suggestion: None,
+                    ..
+                })
+            ));
+        }
+    }
+}
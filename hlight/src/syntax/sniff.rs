@@ -0,0 +1,592 @@
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+/// A sniffed format must match at least this share of the lines.
+const MIN_SCORE: f32 = 0.6;
+
+/// RON nested deeper than this is not sniffed. Each level scans its body, so the depth bounds both the recursion and the work.
+const MAX_RON_DEPTH: usize = 32;
+
+/// Guesses the syntax of a config file from its contents alone, e.g. a blob from an API without a file name.
+///
+/// The candidates are JSON, RON, TOML, YAML and INI. Each of them is scored (from 0 to 1) with lightweight structural checks, e.g. the share of lines that are TOML tables or key-value pairs with a valid TOML value. If several formats get the same score, they are preferred in the order above, because e.g. JSON is also valid YAML.
+///
+/// Returns `None` if no candidate scores high enough, or if the syntax set does not contain it.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{syntax::sniff_syntax, HighLightRes};
+///
+/// let set = HighLightRes::static_syntax_set();
+///
+/// let s = "[server]\nhost = example.com\n; comment\n";
+/// assert_eq!(sniff_syntax(set, s).map(|x| &x.name[..]), Some("INI"));
+///
+/// let s = "server:\n  host: example.com\n  ports:\n    - 80\n";
+/// assert_eq!(sniff_syntax(set, s).map(|x| &x.name[..]), Some("YAML"));
+///
+/// assert!(sniff_syntax(set, "Hello, world!").is_none());
+/// ```
+pub fn sniff_syntax<'a>(
+    set: &'a SyntaxSet,
+    contents: &str,
+) -> Option<&'a SyntaxReference> {
+    let mut best: Option<(&SyntaxReference, f32)> = None;
+
+    for (name, score) in sniff_scores(contents) {
+        log::trace!("sniffed {name}: {score}");
+        if score < MIN_SCORE || best.is_some_and(|(_, s)| s >= score) {
+            continue;
+        }
+        if let Some(syntax) = set.find_syntax_by_name(name) {
+            best = Some((syntax, score))
+        }
+    }
+    best.map(|(syntax, _)| syntax)
+}
+
+/// The scores of the candidate syntaxes, in the order of preference.
+fn sniff_scores(contents: &str) -> [(&'static str, f32); 5] {
+    let contents = contents.trim_start_matches('\u{feff}');
+    let lines = contents
+        .lines()
+        .filter(|x| !x.trim().is_empty())
+        .collect::<Vec<_>>();
+
+    [
+        ("JSON", json_score(contents)),
+        ("RON", ron_score(&lines)),
+        ("TOML", toml_score(&lines)),
+        ("YAML", yaml_score(&lines)),
+        ("INI", ini_score(&lines)),
+    ]
+}
+
+fn ratio(matched: usize, total: usize) -> f32 {
+    match total {
+        0 => 0.0,
+        _ => matched as f32 / total as f32,
+    }
+}
+
+/// Replaces every string quoted with one of `quotes` by `0`, so that only the structure is left.
+///
+/// Returns `None` if a string is not closed.
+fn strip_strings(s: &str, quotes: &[char]) -> Option<String> {
+    let mut stripped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if !quotes.contains(&c) {
+            stripped.push(c);
+            continue;
+        }
+        loop {
+            match chars.next()? {
+                '\\' => {
+                    chars.next()?;
+                }
+                x if x == c => break,
+                _ => {}
+            }
+        }
+        stripped.push('0');
+    }
+    Some(stripped)
+}
+
+/// Whether `()`, `[]` and `{}` are balanced and properly nested.
+fn is_balanced(s: &str) -> bool {
+    let mut stack = Vec::new();
+    for c in s.chars() {
+        match c {
+            '(' | '[' | '{' => stack.push(c),
+            ')' | ']' | '}' => {
+                let open = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if stack.pop() != Some(open) {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+    stack.is_empty()
+}
+
+/// An object or array with only strings, numbers, `true`, `false` and `null` in it.
+fn json_score(contents: &str) -> f32 {
+    let s = contents.trim();
+    if !matches!(
+        (s.chars().next(), s.chars().last()),
+        (Some('{'), Some('}')) | (Some('['), Some(']'))
+    ) {
+        return 0.0;
+    }
+    let Some(s) = strip_strings(s, &['"']) else {
+        return 0.0;
+    };
+    let is_value = |x: &str| {
+        matches!(x, "true" | "false" | "null")
+            || x.starts_with(|c: char| c == '-' || c.is_ascii_digit())
+                && x.parse::<f64>().is_ok()
+    };
+    let valid = is_balanced(&s)
+        && s.split(|c: char| c.is_whitespace() || "{}[]:,".contains(c))
+            .filter(|x| !x.is_empty())
+            .all(is_value);
+
+    match valid {
+        true => 1.0,
+        _ => 0.0,
+    }
+}
+
+/// A struct (e.g. `Config(..)` or `(..)`) at the top level, or an `#![enable(..)]` attribute.
+///
+/// The struct must make up the whole document, and its body must be `field: value` pairs or a tuple of RON values, so that function calls like `print('a')` or `main() { .. }` are not mistaken for RON.
+fn ron_score(lines: &[&str]) -> f32 {
+    let s = lines
+        .iter()
+        .filter(|x| !x.trim_start().starts_with("//"))
+        .copied()
+        .collect::<Vec<_>>()
+        .join("\n");
+    let Some(s) = strip_strings(s.trim(), &['"']) else {
+        return 0.0;
+    };
+    // Strings are stripped, so `//` only starts comments.
+    let s = s
+        .lines()
+        .map(|x| x.split_once("//").map_or(x, |(code, _)| code))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let s = s.trim();
+
+    let valid =
+        is_balanced(s) && (s.starts_with("#![enable(") || is_ron_struct(s, 0));
+    match valid {
+        true => 1.0,
+        _ => 0.0,
+    }
+}
+
+/// `Name(..)` or `(..)` with fields or a tuple inside, at the nesting `depth`. Unless it is at the top level, an empty body (e.g. `Unit()`) is accepted as well.
+fn is_ron_struct(s: &str, depth: usize) -> bool {
+    if depth > MAX_RON_DEPTH {
+        return false;
+    }
+    let body =
+        s.trim_start_matches(|c: char| c.is_ascii_alphanumeric() || c == '_');
+    let name = &s[..s.len() - body.len()];
+    // Types are UpperCamelCase, functions are usually not.
+    if name.starts_with(|c: char| !c.is_ascii_uppercase()) {
+        return false;
+    }
+    let Some(body) = body.strip_prefix('(').and_then(|x| x.strip_suffix(')'))
+    else {
+        return false;
+    };
+    // e.g. `(a)(b)`
+    if !is_balanced(body) {
+        return false;
+    }
+    let items = split_top_level(body, ',');
+    if items.is_empty() {
+        return depth != 0;
+    }
+    let is_field = |x: &str| {
+        x.split_once(':').is_some_and(|(k, v)| {
+            let k = k.trim();
+            k.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && is_ron_value(v, depth + 1)
+        })
+    };
+    items.iter().all(|x| is_field(x))
+        || items.iter().all(|x| is_ron_value(x, depth + 1))
+}
+
+/// A number, `true`, `false`, a (stripped) string, a unit variant (e.g. `None`), a list, a map, or a struct, at the nesting `depth`.
+fn is_ron_value(value: &str, depth: usize) -> bool {
+    if depth > MAX_RON_DEPTH {
+        return false;
+    }
+    let v = value.trim();
+    let number = v.replace('_', "");
+    let unsigned = number.trim_start_matches(['+', '-']);
+    let is_list = |inner: &str| {
+        split_top_level(inner, ',')
+            .iter()
+            .all(|x| is_ron_value(x, depth + 1))
+    };
+    let is_map = |inner: &str| {
+        split_top_level(inner, ',').iter().all(|x| {
+            x.split_once(':').is_some_and(|(k, v)| {
+                is_ron_value(k, depth + 1) && is_ron_value(v, depth + 1)
+            })
+        })
+    };
+
+    matches!(v, "true" | "false")
+        || unsigned.starts_with(|c: char| c.is_ascii_digit())
+            && (unsigned.parse::<f64>().is_ok()
+                || ["0x", "0b", "0o"].iter().any(|p| {
+                    unsigned.strip_prefix(p).is_some_and(|x| {
+                        x.chars().all(|c| c.is_ascii_hexdigit())
+                    })
+                }))
+        || v.starts_with(|c: char| c.is_ascii_uppercase())
+            && v.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        || v.strip_prefix('[')
+            .and_then(|x| x.strip_suffix(']'))
+            .is_some_and(|x| is_balanced(x) && is_list(x))
+        || v.strip_prefix('{')
+            .and_then(|x| x.strip_suffix('}'))
+            .is_some_and(|x| is_balanced(x) && is_map(x))
+        || is_ron_struct(v, depth)
+}
+
+/// Splits `s` at every `sep` outside of brackets, without empty items (e.g. after a trailing comma).
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut items = Vec::new();
+    let (mut depth, mut start) = (0_usize, 0);
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            _ if c == sep && depth == 0 => {
+                items.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    items.push(&s[start..]);
+    items.retain(|x| !x.trim().is_empty());
+    items
+}
+
+fn is_toml_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.split('.').all(|part| {
+            let part = part.trim();
+            let quoted = part.len() >= 2
+                && (part.starts_with('"') && part.ends_with('"')
+                    || part.starts_with('\'') && part.ends_with('\''));
+            quoted
+                || !part.is_empty()
+                    && part.chars().all(|c| {
+                        c.is_ascii_alphanumeric() || c == '_' || c == '-'
+                    })
+        })
+}
+
+/// Strings, numbers, booleans, dates, arrays and inline tables.
+fn is_toml_value(value: &str) -> bool {
+    let value = match value.split_once(" #") {
+        Some((v, _)) if !value.starts_with(['"', '\'']) => v.trim(),
+        _ => value,
+    };
+    let number = value.replace('_', "");
+    let unsigned = number.trim_start_matches(['+', '-']);
+    let is_date = |x: &str| {
+        let b = x.as_bytes();
+        b.len() >= 8
+            && b[..4].iter().all(u8::is_ascii_digit)
+            && b[4] == b'-'
+            && b[5..7].iter().all(u8::is_ascii_digit)
+    };
+    let is_time = |x: &str| {
+        let b = x.as_bytes();
+        b.len() >= 5
+            && b[..2].iter().all(u8::is_ascii_digit)
+            && b[2] == b':'
+            && b[3..5].iter().all(u8::is_ascii_digit)
+    };
+
+    match value.chars().next() {
+        Some('"' | '\'' | '[' | '{') => true,
+        _ => {
+            matches!(value, "true" | "false")
+                || matches!(unsigned, "inf" | "nan")
+                || unsigned.starts_with(|c: char| c.is_ascii_digit())
+                    && (number.parse::<f64>().is_ok()
+                        || ["0x", "0o", "0b"]
+                            .iter()
+                            .any(|p| unsigned.starts_with(p)))
+                || is_date(value)
+                || is_time(value)
+        }
+    }
+}
+
+/// The share of lines that are TOML tables or key-value pairs. Lines in multi-line strings and arrays are skipped.
+fn toml_score(lines: &[&str]) -> f32 {
+    let (mut matched, mut total) = (0, 0);
+    let mut multi_line_string: Option<&str> = None;
+    let mut depth = 0i32;
+    let bracket_delta = |x: &str| match strip_strings(x, &['"', '\'']) {
+        Some(x) => {
+            x.matches(['[', '{']).count() as i32
+                - x.matches([']', '}']).count() as i32
+        }
+        _ => 0,
+    };
+
+    for line in lines {
+        let line = line.trim();
+        if let Some(delimiter) = multi_line_string {
+            if line.contains(delimiter) {
+                multi_line_string = None;
+            }
+            continue;
+        }
+        if depth > 0 {
+            depth += bracket_delta(line);
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        total += 1;
+
+        let is_table = line
+            .strip_prefix("[[")
+            .and_then(|x| x.strip_suffix("]]"))
+            .or_else(|| line.strip_prefix('[')?.strip_suffix(']'))
+            .is_some_and(is_toml_key);
+        if is_table {
+            matched += 1;
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        if !(is_toml_key(key.trim()) && is_toml_value(value)) {
+            continue;
+        }
+        matched += 1;
+
+        multi_line_string = ["\"\"\"", "'''"]
+            .into_iter()
+            .find(|x| value.starts_with(x) && !value[x.len()..].contains(x));
+        if value.starts_with(['[', '{']) {
+            depth = bracket_delta(value).max(0);
+        }
+    }
+    ratio(matched, total)
+}
+
+/// The share of lines that are mapping entries, sequence items or document markers. Lines in block scalars (`|` and `>`) are skipped.
+fn yaml_score(lines: &[&str]) -> f32 {
+    let (mut matched, mut total) = (0, 0);
+    // the indentation of the line that starts a block scalar
+    let mut block_scalar: Option<usize> = None;
+    let indent = |x: &str| x.len() - x.trim_start().len();
+
+    for line in lines {
+        if let Some(i) = block_scalar {
+            if indent(line) > i {
+                continue;
+            }
+            block_scalar = None;
+        }
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') {
+            continue;
+        }
+        total += 1;
+
+        let item = trimmed.strip_prefix('-').filter(|x| {
+            x.is_empty() || x.starts_with(' ') && !x.starts_with(" -")
+        });
+        let entry = item.map(str::trim).unwrap_or(trimmed);
+        let key = entry
+            .split_once(": ")
+            .map(|(key, _)| key)
+            .or_else(|| entry.strip_suffix(':'));
+        let is_key = key.is_some_and(|key| {
+            let key = key.trim();
+            !key.is_empty()
+                && !key.contains(" = ")
+                && (key.starts_with(['"', '\''])
+                    || !key.contains(['"', '[', ']', '{', '}', '=']))
+        });
+
+        if is_key
+            || item.is_some()
+            || matches!(trimmed, "---" | "...")
+            || trimmed.starts_with("--- ")
+            || trimmed.starts_with("%YAML")
+        {
+            matched += 1;
+        }
+        if trimmed.ends_with(['|', '>'])
+            || ["|-", "|+", ">-", ">+"]
+                .iter()
+                .any(|x| trimmed.ends_with(x))
+        {
+            block_scalar = Some(indent(line));
+        }
+    }
+    ratio(matched, total)
+}
+
+/// The share of lines that are sections, key-value pairs (with `=` or `:`) or `;` comments.
+fn ini_score(lines: &[&str]) -> f32 {
+    let (mut matched, mut total) = (0, 0);
+
+    for line in lines {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        total += 1;
+
+        let is_section = line.starts_with('[')
+            && line.ends_with(']')
+            && !line[1..line.len() - 1].contains(['[', ']', ',']);
+        let is_pair = line.find(['=', ':']).is_some_and(|i| {
+            let key = line[..i].trim();
+            !key.is_empty() && !key.starts_with(['-', '[', '{', '"'])
+        });
+        if is_section || is_pair || line.starts_with(';') {
+            matched += 1;
+        }
+    }
+    ratio(matched, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HighLightRes;
+
+    fn sniff(s: &str) -> Option<&'static str> {
+        let set = HighLightRes::static_syntax_set();
+        sniff_syntax(set, s).map(|x| &x.name[..])
+    }
+
+    #[test]
+    fn config_formats() {
+        let json = r#"
+{
+  "name": "hlight",
+  "keywords": ["syntax", "highlighting"],
+  "version": 1.5,
+  "private": false
+}
+"#;
+        assert_eq!(sniff(json), Some("JSON"));
+
+        let ron = r#"
+// comment
+Config(
+    name: "hlight",
+    keywords: ["syntax", "highlighting"],
+    license: Some("Apache-2.0"),
+)
+"#;
+        assert_eq!(sniff(ron), Some("RON"));
+
+        let toml = r#"
+# comment
+[package]
+name = "hlight"
+keywords = [
+    "syntax",
+    "highlighting",
+]
+description = """
+Syntax highlighting: output to terminal, html and svg
+"""
+
+[dependencies.syntect]
+version = "5.0.0"
+default-features = false
+released = 2023-05-28
+"#;
+        assert_eq!(sniff(toml), Some("TOML"));
+
+        let yaml = r#"
+---
+package:
+  name: hlight
+  keywords:
+    - syntax
+    - highlighting
+  description: |
+    Syntax highlighting = terminal, html and svg
+    [output]
+"#;
+        assert_eq!(sniff(yaml), Some("YAML"));
+
+        let ini = r#"
+; comment
+[package]
+name = hlight
+keywords = syntax, highlighting
+
+[remote "origin"]
+url = https://github.com/2moe/hlight
+"#;
+        assert_eq!(sniff(ini), Some("INI"));
+    }
+
+    #[test]
+    fn ambiguous() {
+        // Both TOML and INI, but TOML is preferred.
+        assert_eq!(sniff("[a]\nb = 1\n"), Some("TOML"));
+        // Both YAML and INI, but YAML is preferred.
+        assert_eq!(sniff("a: 1\nb: 2\n"), Some("YAML"));
+        // Neither JSON nor TOML.
+        assert_eq!(sniff("[a, b = 1]"), None);
+        assert_eq!(sniff(""), None);
+        assert_eq!(sniff("Hello, world!\nThis is plain text.\n"), None);
+    }
+
+    #[test]
+    fn function_calls() {
+        // Balanced brackets alone are not RON.
+        assert_eq!(sniff("print('a')\nprint('b')\n"), None);
+        assert_eq!(sniff("print('a')\n"), None);
+        assert_eq!(sniff("main() {\n  run();\n}\n"), None);
+        assert_eq!(sniff("run(x, y)\n"), None);
+        assert_eq!(sniff("Point(1, 2)(3)\n"), None);
+        assert_eq!(sniff("fn main() {}\n"), None);
+
+        assert_eq!(sniff("(800, 600)\n"), Some("RON"));
+        assert_eq!(sniff("#![enable(implicit_some)]\n(a: 1)\n"), Some("RON"));
+        let ron = "Scene( // comment\n  entities: [Some((x: 0x1F)), None],\n  map: {\"a\": 1.5},\n)\n";
+        assert_eq!(sniff(ron), Some("RON"));
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let nested = |open: &str, close: &str, depth| {
+            format!("{}{}\n", open.repeat(depth), close.repeat(depth))
+        };
+        assert_eq!(sniff(&nested("(", ")", 20_000)), None);
+        assert_ne!(sniff(&nested("Config(a: [", "])", 16_000)), Some("RON"));
+        assert_eq!(sniff(&nested("[", "]", 16_000)), Some("JSON"));
+
+        assert_eq!(sniff(&nested("(", ")", MAX_RON_DEPTH)), Some("RON"));
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            strip_strings(r#"{"a\"b": "}"}"#, &['"']).as_deref(),
+            Some("{0: 0}")
+        );
+        assert_eq!(strip_strings(r#"{"a: 1}"#, &['"']), None);
+        assert!(is_balanced("({[]})"));
+        assert!(!is_balanced("({)}"));
+    }
+}
@@ -1,12 +1,18 @@
 use std::borrow::Cow;
 
+use bincode::Options;
 use once_cell::sync::OnceCell;
 pub use syntect::{
     dumps,
     highlighting::{Theme, ThemeSet},
 };
 
-use crate::resource::HighLightRes;
+use crate::{
+    dump::dump_options,
+    error::{Error, LoadError},
+    resource::HighLightRes,
+    syntax::SyntaxSet,
+};
 
 #[cfg(feature = "preset-theme-set")]
 const THEME_SET: &[u8] = include_bytes!(concat!(
@@ -27,32 +33,171 @@ pub const READ_DUMP_DATA_ERR: &str = "Failed to read dump data";
 ///
 /// const THEMES: &[u8] = include_bytes!(concat!(
 ///     env!("CARGO_MANIFEST_DIR"),
-///     "/assets/theme-set.packdump"
+///     "/assets/theme-syntax-set/theme-set.packdump"
 /// ));
 ///
 /// let set = load_theme_set(Some(THEMES));
 /// ```
+///
+/// # Panics
+///
+/// Panics if the data is not an uncompressed theme set dump. Use [`try_load_theme_set`] to handle it.
 pub fn load_theme_set(set: Option<&[u8]>) -> ThemeSet {
-    let msg = READ_DUMP_DATA_ERR;
+    try_load_theme_set(set)
+        .unwrap_or_else(|e| panic!("{READ_DUMP_DATA_ERR}: {e}"))
+}
 
-    match set {
-        Some(x) => dumps::from_uncompressed_data(x).expect(msg),
+/// Loads a set of themes, or returns [`Error::Load`] if the data is corrupted, compressed or a dump of a syntax set.
+///
+/// If the parameter is None, the default theme set is used.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{theme::try_load_theme_set, Error, LoadError};
+///
+/// let err = try_load_theme_set(Some(&[0x78, 0x9C, 0])).unwrap_err();
+/// assert!(matches!(err, Error::Load(LoadError::Compressed)));
+/// ```
+pub fn try_load_theme_set(set: Option<&[u8]>) -> Result<ThemeSet, Error> {
+    let data = match set {
+        Some(x) => x,
         #[cfg(feature = "preset-theme-set")]
-        _ => dumps::from_uncompressed_data(set.unwrap_or(THEME_SET)).expect(msg),
+        _ => THEME_SET,
         #[allow(unreachable_patterns)]
-        _ => ThemeSet::default(),
+        _ => return Ok(ThemeSet::default()),
+    };
+
+    dump_options(data).deserialize(data).map_err(|e| {
+        let found = dump_options(data)
+            .deserialize::<SyntaxSet>(data)
+            .is_ok()
+            .then_some("SyntaxSet");
+        LoadError::new(data, e, "ThemeSet", found).into()
+    })
+}
+
+/// Finds a theme by name.
+///
+/// If the name does not exist, the error contains all available names and the closest one.
+///
+/// # Example
+///
+/// ```
+/// use hlight::{theme::find_theme, Error, HighLightRes};
+///
+/// let set = HighLightRes::static_theme_set();
+/// assert!(find_theme(set, "ayu-dark").is_ok());
+///
+/// match find_theme(set, "ayu-drak") {
+///     Err(Error::UnknownTheme { suggestion, .. }) => {
+///         assert_eq!(suggestion.as_deref(), Some("ayu-dark"))
+///     }
+///     _ => unreachable!(),
+/// }
+/// ```
+pub fn find_theme<'a>(
+    set: &'a ThemeSet,
+    name: &str,
+) -> Result<&'a Theme, Error> {
+    set.themes.get(name).ok_or_else(|| {
+        let available: Vec<_> = set.themes.keys().cloned().collect();
+        let suggestion =
+            closest_name(name, available.iter().map(String::as_str))
+                .map(ToOwned::to_owned);
+
+        Error::UnknownTheme {
+            name: name.to_owned(),
+            available,
+            suggestion,
+        }
+    })
+}
+
+/// Finds the candidate that is most similar to `name`.
+///
+/// A case-insensitive match is preferred, then a candidate containing `name`, then the smallest edit distance (if it is small enough).
+pub(crate) fn closest_name<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let name = name.to_lowercase();
+    let candidates: Vec<_> = candidates
+        .into_iter()
+        .map(|c| (c, c.to_lowercase()))
+        .collect();
+
+    let find = |f: &dyn Fn(&str) -> bool| {
+        candidates
+            .iter()
+            .find(|(_, lower)| f(lower))
+            .map(|(c, _)| *c)
+    };
+
+    find(&|c| c == name)
+        .or_else(|| find(&|c| !name.is_empty() && c.contains(&name)))
+        .or_else(|| {
+            let max_distance = (name.chars().count() / 3).max(2);
+            candidates
+                .iter()
+                .map(|(c, lower)| (c, edit_distance(&name, lower)))
+                .filter(|(_, d)| *d <= max_distance)
+                .min_by_key(|(_, d)| *d)
+                .map(|(c, _)| *c)
+        })
+}
+
+/// Levenshtein distance
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<_> = b.chars().collect();
+    let mut row: Vec<_> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(cur).min(row[j])
+            };
+            prev = cur;
+        }
     }
+    row[b.len()]
 }
 
 impl<'name> HighLightRes<'name> {
     /// Gets the theme or initialize it if it is not already set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the theme name does not exist in the theme set. Use [`HighLightRes::try_theme_once`] to handle it.
     pub fn set_theme_once(&self) -> &Theme {
-        self.get_theme()
-            .get_or_init(|| {
-                let name = self.get_name().as_ref();
-                let set = self.get_theme_set();
-                set.themes[name].to_owned()
-            })
+        self.try_theme_once().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Gets the theme or initialize it if it is not already set.
+    ///
+    /// Returns [`Error::UnknownTheme`] if the theme name does not exist in the theme set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::HighLightRes;
+    /// use std::borrow::Cow;
+    ///
+    /// let mut res = HighLightRes::default();
+    /// *res.get_name_mut() = Cow::from("Dracul");
+    ///
+    /// let err = res.try_theme_once().unwrap_err();
+    /// assert!(err.to_string().contains(r#"did you mean "Dracula"?"#));
+    /// ```
+    pub fn try_theme_once(&self) -> Result<&Theme, Error> {
+        self.get_theme().get_or_try_init(|| {
+            find_theme(self.get_theme_set(), self.get_name()).cloned()
+        })
     }
 
     /// This is the default theme set.
@@ -97,6 +242,31 @@ const fn ayu_dark_theme_name() -> &'static str {
 mod tests {
     use super::*;
 
+    #[test]
+    fn closest() {
+        let names = ["ayu-dark", "ayu-light", "Dracula", "Monokai Extended"];
+        let closest = |name| closest_name(name, names);
+
+        assert_eq!(closest("dracula"), Some("Dracula"));
+        assert_eq!(closest("monokai"), Some("Monokai Extended"));
+        assert_eq!(closest("ayu-lihgt"), Some("ayu-light"));
+        assert_eq!(closest("solarized"), None);
+    }
+
+    #[test]
+    fn unknown_theme() {
+        let set = HighLightRes::static_theme_set();
+        let Err(Error::UnknownTheme {
+            name, available, ..
+        }) = find_theme(set, "none-such")
+        else {
+            panic!("The theme should not exist")
+        };
+
+        assert_eq!(name, "none-such");
+        assert_eq!(available.len(), set.themes.len());
+    }
+
     #[test]
     fn get_theme_set() {
         let themes = &HighLightRes::static_theme_set().themes;
@@ -0,0 +1,233 @@
+use crate::{error::Result, overlay::Overlays, resource::HighLightRes};
+use syntect::{
+    easy::{HighlightLines, ScopeRangeIterator},
+    highlighting::{Style, Theme},
+    parsing::{ParseState, ScopeStack, SyntaxSet},
+    util::LinesWithEndings,
+};
+
+/// An iterator over the highlighted lines of some text, created by [`HighLightRes::tokens`].
+///
+/// Each item holds the `(Style, &str)` spans of one line. The last span of a line includes its line ending, if any.
+pub struct Tokens<'a> {
+    lines: LinesWithEndings<'a>,
+    /// `None` if the theme name is "None".
+    highlight: Option<HighlightLines<'a>>,
+    syntax_set: &'a SyntaxSet,
+    overlays: Overlays<'a>,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Vec<(Style, &'a str)>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.lines.next()?;
+        let ranges = match &mut self.highlight {
+            Some(h) => h
+                .highlight_line(line, self.syntax_set)
+                .map(|ranges| self.overlays.apply(ranges))
+                .map_err(Into::into),
+            _ => Ok(vec![(Style::default(), line)]),
+        };
+        Some(ranges)
+    }
+}
+
+/// An iterator over the scopes of the lines of some text, created by [`HighLightRes::scoped_tokens`].
+///
+/// Each item holds the `(ScopeStack, &str)` spans of one line, where the stack is the full scope path of the span (e.g. `source.toml string.quoted.double.basic.toml`). It does not depend on any theme.
+pub struct ScopedTokens<'a> {
+    lines: LinesWithEndings<'a>,
+    state: ParseState,
+    stack: ScopeStack,
+    syntax_set: &'a SyntaxSet,
+}
+
+impl<'a> ScopedTokens<'a> {
+    fn parse_line(
+        &mut self,
+        line: &'a str,
+    ) -> Result<Vec<(ScopeStack, &'a str)>> {
+        let ops = self.state.parse_line(line, self.syntax_set)?;
+        let mut spans = Vec::new();
+
+        for (range, op) in ScopeRangeIterator::new(&ops, line) {
+            self.stack.apply(op)?;
+            if !range.is_empty() {
+                spans.push((self.stack.clone(), &line[range]));
+            }
+        }
+        Ok(spans)
+    }
+}
+
+impl<'a> Iterator for ScopedTokens<'a> {
+    type Item = Result<Vec<(ScopeStack, &'a str)>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.lines.next()?;
+        Some(self.parse_line(line))
+    }
+}
+
+impl<'name> HighLightRes<'name> {
+    /// Highlights `contents` line by line, without rendering it into any output format.
+    ///
+    /// This is useful for custom renderers (e.g. TUI widgets or GUI text views). The syntax is resolved in the same way as [`gen_syntax_highlight`](crate::gen_syntax_highlight). If the theme name is "None", every line is a single span with `Style::default()`.
+    ///
+    /// The overlays of [`HighLightRes::with_overlays`] are already applied to the spans. If the background is disabled, every span has the background of the theme, except for the overlays.
+    ///
+    /// The text is not sanitized (see [`ControlChars`](crate::ControlChars)), so escape control characters yourself if it is written to a terminal.
+    ///
+    /// Returns [`Error::UnknownTheme`](crate::Error::UnknownTheme) if the theme name does not exist in the theme set, and [`Error::UnknownSyntax`](crate::Error::UnknownSyntax) if the syntax cannot be resolved and the [`Fallback`](crate::syntax::Fallback) of the resolver is an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::HighLightRes;
+    ///
+    /// let res = HighLightRes::default();
+    /// let s = "[main]\nenabled = false\n";
+    ///
+    /// for line in res.tokens("toml", s)? {
+    ///     for (style, text) in line? {
+    ///         print!("{:?}:{text:?} ", style.foreground);
+    ///     }
+    ///     println!();
+    /// }
+    /// # Ok::<(), hlight::Error>(())
+    /// ```
+    pub fn tokens<'a>(
+        &'a self,
+        dst_fmt: &str,
+        contents: &'a str,
+    ) -> Result<Tokens<'a>> {
+        let (highlight, overlays) = match self.get_name().as_ref() {
+            "None" => (None, Overlays::new(self.get_overlays())),
+            _ => (
+                Some(self.highlight_lines(dst_fmt, contents)?),
+                self.overlays(self.try_theme_once()?),
+            ),
+        };
+        Ok(Tokens {
+            lines: LinesWithEndings::from(contents),
+            highlight,
+            syntax_set: self.get_syntax_set(),
+            overlays,
+        })
+    }
+
+    /// Resolves the syntax and theme, and creates a stateful line highlighter.
+    ///
+    /// If `dst_fmt` is unknown, the syntax is sniffed from `contents`.
+    pub(crate) fn highlight_lines(
+        &self,
+        dst_fmt: &str,
+        contents: &str,
+    ) -> Result<HighlightLines<'_>> {
+        let resolved = self.resolve_syntax(dst_fmt, contents)?;
+        let syntax = *resolved.get_syntax();
+
+        log::trace!("ext: {:?}", syntax.file_extensions);
+        log::debug!("syntax:{} ({:?})", syntax.name, resolved.get_reason());
+
+        Ok(HighlightLines::new(syntax, self.try_theme_once()?))
+    }
+
+    /// The overlays to apply to the lines highlighted with `theme`.
+    ///
+    /// If the background is disabled, the scope backgrounds of the theme are dropped, so only the overlays can add a background.
+    pub(crate) fn overlays(&self, theme: &Theme) -> Overlays<'_> {
+        let background = match self.get_background() {
+            true => None,
+            _ => theme.settings.background,
+        };
+        Overlays::new(self.get_overlays()).with_background(background)
+    }
+
+    /// Parses `contents` line by line into scope spans, without applying any theme.
+    ///
+    /// Returns [`Error::UnknownSyntax`](crate::Error::UnknownSyntax) if the syntax cannot be resolved and the [`Fallback`](crate::syntax::Fallback) of the resolver is an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hlight::HighLightRes;
+    ///
+    /// let res = HighLightRes::default();
+    ///
+    /// for line in res.scoped_tokens("toml", "enabled = false\n")? {
+    ///     for (stack, text) in line? {
+    ///         println!("{text:?}: {stack:?}");
+    ///     }
+    /// }
+    /// # Ok::<(), hlight::Error>(())
+    /// ```
+    pub fn scoped_tokens<'a>(
+        &'a self,
+        dst_fmt: &str,
+        contents: &'a str,
+    ) -> Result<ScopedTokens<'a>> {
+        let syntax = self.resolve_syntax(dst_fmt, contents)?;
+
+        Ok(ScopedTokens {
+            lines: LinesWithEndings::from(contents),
+            state: ParseState::new(syntax.get_syntax()),
+            stack: ScopeStack::new(),
+            syntax_set: self.get_syntax_set(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntect::parsing::Scope;
+
+    const S: &str = "[main]\nenabled = false\n";
+
+    #[test]
+    fn tokens() -> Result<()> {
+        let res = HighLightRes::default();
+        let lines = res.tokens("toml", S)?.collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(lines.len(), 2);
+        for (line, expected) in lines.iter().zip(S.split_inclusive('\n')) {
+            let text = line.iter().map(|(_, s)| *s).collect::<String>();
+            assert_eq!(text, expected);
+        }
+        assert!(lines[1].len() > 1);
+        Ok(())
+    }
+
+    #[test]
+    fn plain_tokens() -> Result<()> {
+        let mut res = HighLightRes::default();
+        *res.get_name_mut() = "None".into();
+
+        let lines = res.tokens("toml", S)?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(lines[0], [(Style::default(), "[main]\n")]);
+        Ok(())
+    }
+
+    #[test]
+    fn scoped_tokens() -> Result<()> {
+        let res = HighLightRes::default();
+        let toml = Scope::new("source.toml").expect("Invalid scope");
+
+        for line in res.scoped_tokens("toml", S)? {
+            for (stack, _) in line? {
+                assert_eq!(stack.as_slice().first(), Some(&toml));
+            }
+        }
+
+        let lines =
+            res.scoped_tokens("toml", S)?.collect::<Result<Vec<_>>>()?;
+        let (stack, _) = lines[1]
+            .iter()
+            .find(|(_, text)| *text == "false")
+            .expect("No `false` token");
+        assert!(format!("{stack:?}").contains("constant.language"));
+        Ok(())
+    }
+}